@@ -0,0 +1,135 @@
+use crate::models::*;
+use crate::utils::*;
+use poise::serenity_prelude::{self as serenity};
+use std::{
+    collections::HashSet,
+    env, fs,
+    path::{Path, PathBuf},
+};
+const DISCORD_ATTACHMENT_HOSTS: &str = "cdn.discordapp.com,media.discordapp.net";
+fn env_value(name: &str) -> Option<String> {
+    env::var(name).ok().filter(|value| !value.trim().is_empty())
+}
+fn is_allowed_guild(guild_id: Option<serenity::GuildId>) -> bool {
+    let Some(guilds) = env_value("ALLOWED_GUILDS") else {
+        return true;
+    };
+    guild_id.is_some_and(|guild_id| {
+        guilds
+            .split(',')
+            .any(|allowed| allowed.trim() == guild_id.to_string())
+    })
+}
+fn required_permission() -> serenity::Permissions {
+    env_value("IMPORT_PERMISSION")
+        .and_then(|name| serenity::Permissions::from_name(&name.trim().to_ascii_uppercase()))
+        .unwrap_or(serenity::Permissions::MANAGE_GUILD)
+}
+pub fn member_allowed(member: &serenity::Member, permissions: serenity::Permissions) -> bool {
+    if permissions.administrator() || permissions.contains(required_permission()) {
+        return true;
+    }
+    env_value("IMPORT_ROLES").is_some_and(|roles| {
+        roles.split(',').any(|role| {
+            member
+                .roles
+                .iter()
+                .any(|member_role| role.trim() == member_role.to_string())
+        })
+    })
+}
+async fn has_member_access(ctx: Context<'_>, guild_id: serenity::GuildId) -> bool {
+    let Some(member) = ctx.author_member().await else {
+        return false;
+    };
+    let permissions = match member.permissions {
+        Some(permissions) => permissions,
+        None => match guild_id.to_partial_guild(ctx).await {
+            Ok(guild) => guild.member_permissions(&member),
+            Err(_) => return false,
+        },
+    };
+    member_allowed(&member, permissions)
+}
+pub async fn check_access(ctx: Context<'_>) -> Result<bool, Error> {
+    let allowed = match ctx.guild_id() {
+        Some(guild_id) => {
+            is_allowed_guild(Some(guild_id)) && has_member_access(ctx, guild_id).await
+        }
+        None => ctx.framework().options().owners.contains(&ctx.author().id),
+    };
+    if !allowed {
+        ctx.send(
+            poise::CreateReply::default()
+                .content("You are not allowed to manage imports here.")
+                .ephemeral(true),
+        )
+        .await?;
+    }
+    Ok(allowed)
+}
+pub fn interaction_allowed(
+    interaction: &serenity::ComponentInteraction,
+    owners: &HashSet<serenity::UserId>,
+) -> bool {
+    match (interaction.guild_id, &interaction.member) {
+        (Some(guild_id), Some(member)) => {
+            is_allowed_guild(Some(guild_id))
+                && member_allowed(member, member.permissions.unwrap_or_default())
+        }
+        (Some(_), None) => false,
+        (None, _) => owners.contains(&interaction.user.id),
+    }
+}
+fn base_dirs(guild_id: Option<serenity::GuildId>) -> Option<Vec<PathBuf>> {
+    let entries = env_value("IMPORT_BASE_DIRS")?;
+    let dirs = entries
+        .split(';')
+        .filter_map(|entry| match entry.split_once('=') {
+            Some((guild, dir)) if guild.trim().parse::<u64>().is_ok() => guild_id
+                .filter(|guild_id| guild.trim() == guild_id.to_string())
+                .map(|_| dir),
+            _ => Some(entry),
+        })
+        .map(str::trim)
+        .filter(|dir| !dir.is_empty())
+        .filter_map(|dir| fs::canonicalize(dir).ok())
+        .collect();
+    Some(dirs)
+}
+pub fn url_allowed(url: &str) -> bool {
+    env_value("IMPORT_URL_HOSTS")
+        .is_none_or(|hosts| url_host_in(url, &hosts) || url_host_in(url, DISCORD_ATTACHMENT_HOSTS))
+}
+pub fn check_input(input: &str, guild_id: Option<serenity::GuildId>) -> Result<(), String> {
+    if is_url(input) {
+        return if url_allowed(input) {
+            Ok(())
+        } else {
+            Err(format!("{input} is not on an allowed host"))
+        };
+    }
+    let Some(dirs) = base_dirs(guild_id) else {
+        return Err(format!(
+            "{input} is a local path, which needs IMPORT_BASE_DIRS to be set"
+        ));
+    };
+    let path = Path::new(input);
+    let target = if path
+        .file_name()
+        .and_then(|name| name.to_str())
+        .is_some_and(is_glob)
+    {
+        path.parent()
+            .filter(|parent| !parent.as_os_str().is_empty())
+            .unwrap_or(Path::new("."))
+    } else {
+        path
+    };
+    let target = fs::canonicalize(target).map_err(|_| format!("{input} not found"))?;
+    if dirs.iter().any(|dir| target.starts_with(dir)) {
+        Ok(())
+    } else {
+        Err(format!("{input} is outside the allowed directories"))
+    }
+}
@@ -0,0 +1,110 @@
+use std::{
+    fs,
+    io::{self, BufReader, Read},
+    path::{Component, Path},
+};
+const TAR_MAGIC_OFFSET: usize = 257;
+#[derive(Clone, Copy, PartialEq)]
+pub enum ArchiveKind {
+    Zip,
+    Tar,
+    TarGzip,
+    TarXz,
+    TarZstd,
+    TarBzip2,
+    SevenZip,
+}
+pub fn detect_archive(path: &Path) -> Option<ArchiveKind> {
+    let mut header = [0; TAR_MAGIC_OFFSET + 5];
+    let file = fs::File::open(path).ok()?;
+    let read = file.take(header.len() as u64).read(&mut header).ok()?;
+    let header = &header[..read];
+    let kind = if header.starts_with(b"PK\x03\x04") || header.starts_with(b"PK\x05\x06") {
+        ArchiveKind::Zip
+    } else if header.starts_with(&[0x1F, 0x8B]) {
+        ArchiveKind::TarGzip
+    } else if header.starts_with(&[0xFD, b'7', b'z', b'X', b'Z', 0x00]) {
+        ArchiveKind::TarXz
+    } else if header.starts_with(&[0x28, 0xB5, 0x2F, 0xFD]) {
+        ArchiveKind::TarZstd
+    } else if header.starts_with(b"BZh") {
+        ArchiveKind::TarBzip2
+    } else if header.starts_with(&[b'7', b'z', 0xBC, 0xAF, 0x27, 0x1C]) {
+        ArchiveKind::SevenZip
+    } else if header.get(TAR_MAGIC_OFFSET..) == Some(b"ustar") {
+        ArchiveKind::Tar
+    } else {
+        return None;
+    };
+    Some(kind)
+}
+fn is_enclosed(path: &Path) -> bool {
+    path.components()
+        .all(|component| matches!(component, Component::Normal(_) | Component::CurDir))
+}
+fn extract_zip(zip_path: &Path, base_dir: &Path) -> Result<(), String> {
+    let file = fs::File::open(zip_path).map_err(|e| format!("Error opening zip: {e}"))?;
+    let mut archive = zip::ZipArchive::new(file).map_err(|e| format!("Error reading zip: {e}"))?;
+    let create_dir =
+        |path: &Path| fs::create_dir_all(path).map_err(|e| format!("Create dir failed: {e}"));
+    for i in 0..archive.len() {
+        let mut entry = archive
+            .by_index(i)
+            .map_err(|e| format!("Zip entry error: {e}"))?;
+        let output_path = match entry.enclosed_name() {
+            Some(path) => base_dir.join(path),
+            None => base_dir.join(entry.mangled_name()),
+        };
+        if entry.is_dir() || entry.name().ends_with('/') {
+            create_dir(&output_path)?;
+            continue;
+        }
+        if let Some(parent) = output_path.parent() {
+            create_dir(parent)?;
+        }
+        let mut output_file =
+            fs::File::create(&output_path).map_err(|e| format!("Create file failed: {e}"))?;
+        io::copy(&mut entry, &mut output_file).map_err(|e| format!("Write file failed: {e}"))?;
+    }
+    Ok(())
+}
+fn extract_tar(reader: impl Read, base_dir: &Path) -> Result<(), String> {
+    tar::Archive::new(reader)
+        .unpack(base_dir)
+        .map_err(|e| format!("Error reading tar: {e}"))
+}
+fn extract_seven_zip(path: &Path, base_dir: &Path) -> Result<(), String> {
+    sevenz_rust::decompress_file_with_extract_fn(path, base_dir, |entry, reader, dest| {
+        if !is_enclosed(Path::new(entry.name())) {
+            return Ok(true);
+        }
+        sevenz_rust::default_entry_extract_fn(entry, reader, dest)
+    })
+    .map_err(|e| format!("Error reading 7z: {e}"))
+}
+pub fn extract_archive_to_temp(
+    path: &Path,
+    kind: ArchiveKind,
+) -> Result<tempfile::TempDir, String> {
+    let tempdir = tempfile::tempdir().map_err(|e| format!("Error creating temp dir: {e}"))?;
+    let base_dir = tempdir.path();
+    let open = || {
+        fs::File::open(path)
+            .map(BufReader::new)
+            .map_err(|e| format!("Error opening archive: {e}"))
+    };
+    match kind {
+        ArchiveKind::Zip => extract_zip(path, base_dir)?,
+        ArchiveKind::Tar => extract_tar(open()?, base_dir)?,
+        ArchiveKind::TarGzip => extract_tar(flate2::read::MultiGzDecoder::new(open()?), base_dir)?,
+        ArchiveKind::TarXz => extract_tar(lzma_rust2::XzReader::new(open()?, true), base_dir)?,
+        ArchiveKind::TarZstd => {
+            let decoder = zstd::Decoder::with_buffer(open()?)
+                .map_err(|e| format!("Error reading zstd: {e}"))?;
+            extract_tar(decoder, base_dir)?
+        }
+        ArchiveKind::TarBzip2 => extract_tar(bzip2::read::MultiBzDecoder::new(open()?), base_dir)?,
+        ArchiveKind::SevenZip => extract_seven_zip(path, base_dir)?,
+    }
+    Ok(tempdir)
+}
@@ -0,0 +1,212 @@
+use crate::models::*;
+use crate::scheduler::*;
+use crate::utils::*;
+use serde::Deserialize;
+use std::{
+    collections::HashMap,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+const REFRESH_URLS_ENDPOINT: &str = "https://discord.com/api/v10/attachments/refresh-urls";
+const ATTACHMENT_REFRESH_ROUTE: u64 = 0;
+const MAX_REFRESH_URLS: usize = 50;
+const SIGNATURE_PARAMS: [&str; 3] = ["ex", "is", "hm"];
+#[derive(Deserialize)]
+struct RefreshedUrls {
+    refreshed_urls: Vec<RefreshedUrl>,
+}
+#[derive(Deserialize)]
+struct RefreshedUrl {
+    original: String,
+    refreshed: String,
+}
+pub fn is_signed_cdn_url(url: &str) -> bool {
+    reqwest::Url::parse(url).is_ok_and(|url| {
+        matches!(
+            url.host_str(),
+            Some("cdn.discordapp.com" | "media.discordapp.net")
+        ) && SIGNATURE_PARAMS
+            .iter()
+            .all(|param| url.query_pairs().any(|(key, _)| key == *param))
+    })
+}
+pub fn is_expired_cdn_url(url: &str) -> bool {
+    if !is_signed_cdn_url(url) {
+        return false;
+    }
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+    reqwest::Url::parse(url).is_ok_and(|url| {
+        url.query_pairs()
+            .find(|(key, _)| key == "ex")
+            .and_then(|(_, ex)| u64::from_str_radix(&ex, 16).ok())
+            .is_none_or(|expires| expires <= now)
+    })
+}
+fn refresh_failure(error: reqwest::Error) -> SendFailure {
+    SendFailure {
+        transient: error.is_timeout()
+            || error.is_connect()
+            || error
+                .status()
+                .is_some_and(|status| status.as_u16() == 429 || status.is_server_error()),
+        message: error.to_string(),
+        retry_after: None,
+    }
+}
+async fn post_refresh_urls(
+    client: &reqwest::Client,
+    token: &str,
+    urls: &[String],
+) -> Result<RefreshedUrls, SendFailure> {
+    let response = client
+        .post(REFRESH_URLS_ENDPOINT)
+        .header(reqwest::header::AUTHORIZATION, token)
+        .header(reqwest::header::CONTENT_TYPE, "application/json")
+        .body(serde_json::json!({ "attachment_urls": urls }).to_string())
+        .send()
+        .await
+        .map_err(refresh_failure)?;
+    let retry_after = response
+        .headers()
+        .get(reqwest::header::RETRY_AFTER)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.parse::<f64>().ok())
+        .and_then(|seconds| Duration::try_from_secs_f64(seconds).ok());
+    let response = response.error_for_status().map_err(|e| SendFailure {
+        retry_after,
+        ..refresh_failure(e)
+    })?;
+    let body = response.bytes().await.map_err(refresh_failure)?;
+    serde_json::from_slice(&body).map_err(|e| SendFailure {
+        message: format!("Error parsing refreshed URLs: {e}"),
+        transient: false,
+        retry_after: None,
+    })
+}
+async fn refresh_cdn_urls(data: &Data, token: &str, urls: &[String]) -> HashMap<String, String> {
+    let mut refreshed = HashMap::new();
+    for chunk in urls.chunks(MAX_REFRESH_URLS) {
+        let response = data
+            .scheduler
+            .run(ATTACHMENT_REFRESH_ROUTE, || {
+                post_refresh_urls(&data.http_client, token, chunk)
+            })
+            .await;
+        if let Ok(body) = response {
+            refreshed.extend(
+                body.refreshed_urls
+                    .into_iter()
+                    .map(|url| (url.original, url.refreshed)),
+            );
+        }
+    }
+    refreshed
+}
+pub async fn refresh_cdn_url(data: &Data, token: &str, url: String) -> String {
+    if !is_expired_cdn_url(&url) {
+        return url;
+    }
+    let mut refreshed = refresh_cdn_urls(data, token, std::slice::from_ref(&url)).await;
+    refreshed.remove(&url).unwrap_or(url)
+}
+pub async fn refresh_upcoming_urls<'a>(
+    data: &Data,
+    token: &str,
+    messages: impl Iterator<Item = &'a mut MessageInfo>,
+    filter: impl Fn(&AttachmentInfo) -> bool,
+) {
+    let mut attachments: Vec<&mut AttachmentInfo> = messages
+        .flat_map(|message| message.attachments.iter_mut())
+        .filter(|attachment| is_expired_cdn_url(&attachment.url) && filter(attachment))
+        .collect();
+    if attachments.is_empty() {
+        return;
+    }
+    let urls: Vec<String> = attachments
+        .iter()
+        .map(|attachment| attachment.url.clone())
+        .collect();
+    let refreshed = refresh_cdn_urls(data, token, &urls).await;
+    for attachment in &mut attachments {
+        if let Some(fresh_url) = refreshed.get(&attachment.url) {
+            attachment.url = fresh_url.clone();
+        }
+    }
+}
+async fn fetch_attachment(client: &reqwest::Client, url: &str, filename: String) -> MediaSource {
+    let response = client
+        .get(url)
+        .send()
+        .await
+        .and_then(|response| response.error_for_status());
+    let Ok(mut response) = response else {
+        return MediaSource::Unavailable(filename);
+    };
+    if response
+        .content_length()
+        .is_some_and(|size| size > MAX_UPLOAD_SIZE)
+    {
+        return MediaSource::Remote(url.to_string());
+    }
+    let mut data = Vec::new();
+    loop {
+        match response.chunk().await {
+            Ok(Some(chunk)) => {
+                data.extend_from_slice(&chunk);
+                if data.len() as u64 > MAX_UPLOAD_SIZE {
+                    return MediaSource::Remote(url.to_string());
+                }
+            }
+            Ok(None) => return MediaSource::Fetched(data, filename),
+            Err(_) => return MediaSource::Unavailable(filename),
+        }
+    }
+}
+pub async fn refresh_expired_sources(
+    data: &Data,
+    token: &str,
+    sources: Vec<MediaSource>,
+) -> Vec<MediaSource> {
+    if !sources
+        .iter()
+        .any(|source| matches!(source, MediaSource::Remote(url) if is_signed_cdn_url(url)))
+    {
+        return sources;
+    }
+    let expired: Vec<String> = sources
+        .iter()
+        .filter_map(|source| match source {
+            MediaSource::Remote(url) if is_expired_cdn_url(url) => Some(url.clone()),
+            _ => None,
+        })
+        .collect();
+    let refreshed = if expired.is_empty() {
+        HashMap::new()
+    } else {
+        refresh_cdn_urls(data, token, &expired).await
+    };
+    let mut result = Vec::with_capacity(sources.len());
+    for source in sources {
+        let MediaSource::Remote(url) = source else {
+            result.push(source);
+            continue;
+        };
+        if !is_signed_cdn_url(&url) {
+            result.push(MediaSource::Remote(url));
+            continue;
+        }
+        let filename = file_name_from_url(&url);
+        let fresh_url = if is_expired_cdn_url(&url) {
+            refreshed.get(&url)
+        } else {
+            Some(&url)
+        };
+        result.push(match fresh_url {
+            Some(fresh_url) => fetch_attachment(&data.http_client, fresh_url, filename).await,
+            None => MediaSource::Unavailable(filename),
+        });
+    }
+    result
+}
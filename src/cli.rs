@@ -1,8 +1,22 @@
+use crate::access::*;
+use crate::cdn::*;
+use crate::download::*;
+use crate::dry_run::*;
+use crate::jobs::*;
 use crate::models::*;
+use crate::progress::*;
+use crate::report::*;
+use crate::scheduler::*;
+use crate::source::*;
+use crate::stream::*;
 use crate::utils::*;
 use poise::serenity_prelude::{self as serenity, EditMessage};
-use std::{collections::HashSet, path::PathBuf};
-use tokio::time;
+use std::{
+    collections::{HashSet, VecDeque},
+    sync::Arc,
+    time::{Duration, Instant},
+};
+use tokio::{sync::watch, time};
 fn build_completion_message(
     export: &Export,
     no_guild: bool,
@@ -16,30 +30,6 @@ fn build_completion_message(
         format!("Successfully imported {footer_text}")
     }
 }
-fn select_messages(
-    messages: &[MessageInfo],
-    range_start: Option<usize>,
-    range_end: Option<usize>,
-    first: Option<usize>,
-    last: Option<usize>,
-) -> &[MessageInfo] {
-    let len = messages.len();
-    if let (Some(s), Some(e)) = (range_start, range_end) {
-        if s <= e && s < len {
-            return &messages[s..=(e.min(len - 1))];
-        }
-    } else if let Some(n) = first {
-        if n > 0 {
-            return &messages[0..n.min(len)];
-        }
-    } else if let Some(n) = last {
-        if n > 0 {
-            let start = len.saturating_sub(n);
-            return &messages[start..];
-        }
-    }
-    messages
-}
 fn split_args(input: &str) -> Vec<String> {
     let mut tokens = Vec::new();
     let mut current = String::new();
@@ -74,6 +64,17 @@ fn parse_options(arguments: &[String]) -> Result<ImportOptions, String> {
             Err(format!("Missing value for {flag}"))
         }
     }
+    fn parse_value<'a>(
+        arguments: &'a [String],
+        index: &mut usize,
+        flag: &str,
+    ) -> Result<&'a str, String> {
+        *index += 1;
+        arguments
+            .get(*index)
+            .map(|value| value.as_str())
+            .ok_or_else(|| format!("Missing value for {flag}"))
+    }
     let mut options = ImportOptions::default();
     let mut index = 0;
     while index < arguments.len() {
@@ -92,6 +93,73 @@ fn parse_options(arguments: &[String]) -> Result<ImportOptions, String> {
             "--disable-button" => options.disable_button = true,
             "--accent-color" => options.accent_color = true,
             "--current-avatar" => options.current_avatar = true,
+            "--reaction-counts" => options.reaction_counts = true,
+            "--skip-bots" => options.skip_bots = true,
+            "--dry-run" => options.dry_run = true,
+            "--author" => {
+                let value = parse_value(arguments, &mut index, "--author")?;
+                options.authors.push(value.to_string());
+            }
+            "--exclude-author" => {
+                let value = parse_value(arguments, &mut index, "--exclude-author")?;
+                options.excluded_authors.push(value.to_string());
+            }
+            "--after" => {
+                let value = parse_value(arguments, &mut index, "--after")?;
+                options.after = Some(parse_time_bound(value)?);
+            }
+            "--before" => {
+                let value = parse_value(arguments, &mut index, "--before")?;
+                options.before = Some(parse_time_bound(value)?);
+            }
+            "--source-channel" => {
+                let value = parse_value(arguments, &mut index, "--source-channel")?;
+                options.source_channel = Some(value.to_string());
+            }
+            "--guild-name" => {
+                let value = parse_value(arguments, &mut index, "--guild-name")?;
+                options.guild_name = Some(value.to_string());
+            }
+            "--channel-name" => {
+                let value = parse_value(arguments, &mut index, "--channel-name")?;
+                options.channel_name = Some(value.to_string());
+            }
+            "--from-id" => {
+                let value = parse_value(arguments, &mut index, "--from-id")?;
+                options.from_id = Some(value.to_string());
+            }
+            "--to-id" => {
+                let value = parse_value(arguments, &mut index, "--to-id")?;
+                options.to_id = Some(value.to_string());
+            }
+            "--from-time" => {
+                let value = parse_value(arguments, &mut index, "--from-time")?;
+                options.from_time = Some(parse_time_bound(value)?);
+            }
+            "--to-time" => {
+                let value = parse_value(arguments, &mut index, "--to-time")?;
+                options.to_time = Some(parse_time_bound(value)?);
+            }
+            "--contains" => {
+                let value = parse_value(arguments, &mut index, "--contains")?;
+                options.contains = Some(
+                    regex::Regex::new(value)
+                        .map_err(|e| format!("Invalid regex for --contains: {e}"))?,
+                );
+            }
+            "--has" => {
+                let content = match parse_value(arguments, &mut index, "--has")? {
+                    "attachment" => MessageContent::Attachment,
+                    "image" => MessageContent::Image,
+                    "reaction" => MessageContent::Reaction,
+                    other => {
+                        return Err(format!(
+                            "Invalid value for --has: {other}. Use attachment, image or reaction"
+                        ))
+                    }
+                };
+                options.has.push(content);
+            }
             "--range" => {
                 index += 1;
                 if index < arguments.len() {
@@ -116,6 +184,28 @@ fn parse_options(arguments: &[String]) -> Result<ImportOptions, String> {
             "--range-end" => {
                 options.range_end = Some(parse_option(arguments, &mut index, "--range-end")?);
             }
+            "--start-at" => {
+                let value = parse_value(arguments, &mut index, "--start-at")?;
+                let start_at = parse_date_time(value)
+                    .ok_or_else(|| format!("Invalid value for --start-at: {value}"))?;
+                options.start_at = Some(start_at);
+            }
+            "--messages-per-minute" => {
+                let rate = parse_option(arguments, &mut index, "--messages-per-minute")?;
+                if rate == 0 || rate > MAX_MESSAGES_PER_MINUTE as usize {
+                    return Err(format!(
+                        "--messages-per-minute must be between 1 and {MAX_MESSAGES_PER_MINUTE}"
+                    ));
+                }
+                options.messages_per_minute = Some(rate as u32);
+            }
+            "--quiet-hours" => {
+                let value = parse_value(arguments, &mut index, "--quiet-hours")?;
+                options.quiet_hours = Some(parse_quiet_hours(value)?);
+            }
+            "--priority" => {
+                options.priority = parse_option(arguments, &mut index, "--priority")?;
+            }
             "--first" => {
                 options.first = Some(parse_option(arguments, &mut index, "--first")?);
             }
@@ -132,29 +222,95 @@ fn parse_options(arguments: &[String]) -> Result<ImportOptions, String> {
     if options.disable_button && !options.button {
         return Err("--disable-button can only be used with --button".to_string());
     }
+    if options.reaction_counts && (options.button || options.no_reactions) {
+        return Err("--reaction-counts cannot be used with --button or --no-reactions".to_string());
+    }
     if options.no_embed && !options.outside {
         return Err("--no-embed can only be used with --outside".to_string());
     }
     Ok(options)
 }
-fn set_cancellation(ctx: &Context<'_>, value: bool) {
-    let mut lock = ctx.data().cancellation_flags.lock().unwrap();
-    lock.insert(ctx.channel_id(), value);
+async fn update_progress(
+    ctx: Context<'_>,
+    progress_message: &mut Option<serenity::Message>,
+    progress: &ImportProgress,
+    status: &str,
+    components: Vec<serenity::CreateActionRow>,
+) {
+    if let Some(message) = progress_message {
+        let edit = EditMessage::new()
+            .content(format_progress(progress, status))
+            .components(components);
+        let _ = message.edit(ctx, edit).await;
+    }
 }
-fn remove_cancellation(ctx: &Context<'_>) {
-    let mut lock = ctx.data().cancellation_flags.lock().unwrap();
-    lock.remove(&ctx.channel_id());
+async fn wait_while_paused(
+    ctx: Context<'_>,
+    job_id: u64,
+    progress_message: &mut Option<serenity::Message>,
+    progress: &mut ImportProgress,
+) -> JobState {
+    let paused_at = Instant::now();
+    let mut state = job_state(ctx.data(), job_id).unwrap_or(JobState::Cancelled);
+    if state == JobState::Paused {
+        let buttons = progress_buttons(job_id, state);
+        let status = job_status(job_id, "Paused");
+        update_progress(ctx, progress_message, progress, &status, buttons).await;
+        while state == JobState::Paused {
+            time::sleep(PAUSE_POLL_INTERVAL).await;
+            state = job_state(ctx.data(), job_id).unwrap_or(JobState::Cancelled);
+        }
+        progress.paused_for += paused_at.elapsed();
+    }
+    state
 }
-fn is_cancelled(ctx: &Context<'_>) -> bool {
-    ctx.data()
-        .cancellation_flags
-        .lock()
-        .unwrap()
-        .get(&ctx.channel_id())
-        .cloned()
-        .unwrap_or(false)
+async fn wait_for_schedule(
+    ctx: Context<'_>,
+    job_id: u64,
+    options: &ImportOptions,
+    progress_message: &mut Option<serenity::Message>,
+    progress: &mut ImportProgress,
+    last_send: Option<Instant>,
+) -> JobState {
+    let interval = options
+        .messages_per_minute
+        .map(|rate| Duration::from_secs(60) / rate);
+    let mut quiet_since: Option<Instant> = None;
+    loop {
+        let state = job_state(ctx.data(), job_id).unwrap_or(JobState::Cancelled);
+        if state == JobState::Cancelled {
+            return state;
+        }
+        let now = serenity::Timestamp::now().unix_timestamp();
+        let quiet = options
+            .quiet_hours
+            .and_then(|quiet_hours| quiet_hours_remaining(quiet_hours, now));
+        let pacing = last_send
+            .zip(interval)
+            .and_then(|(last_send, interval)| interval.checked_sub(last_send.elapsed()))
+            .filter(|wait| !wait.is_zero());
+        if quiet.is_some() && quiet_since.is_none() {
+            quiet_since = Some(Instant::now());
+            let buttons = progress_buttons(job_id, state);
+            let status = job_status(job_id, "Waiting for quiet hours to end");
+            update_progress(ctx, progress_message, progress, &status, buttons).await;
+        }
+        let Some(wait) = quiet.or(pacing) else {
+            break;
+        };
+        time::sleep(wait.min(PAUSE_POLL_INTERVAL)).await;
+    }
+    if let Some(quiet_since) = quiet_since {
+        progress.paused_for += quiet_since.elapsed();
+    }
+    job_state(ctx.data(), job_id).unwrap_or(JobState::Cancelled)
 }
-async fn show_reaction_users(ctx: Context<'_>, reaction_users: bool, reactions: &[ReactionInfo]) {
+async fn show_reaction_users(
+    ctx: Context<'_>,
+    reaction_users: bool,
+    reactions: &[ReactionInfo],
+    delivery: &mut Delivery,
+) {
     if !reaction_users || reactions.is_empty() {
         return;
     }
@@ -162,24 +318,92 @@ async fn show_reaction_users(ctx: Context<'_>, reaction_users: bool, reactions:
     if reaction_content.is_empty() {
         return;
     }
-    let _ = ctx.say(format!("Reactions:\n{reaction_content}")).await;
-    time::sleep(MESSAGE_DELAY).await;
+    let reply = poise::CreateReply::default().content(format!("Reactions:\n{reaction_content}"));
+    let _ = send_reply(ctx, reply, delivery).await;
 }
 async fn attach_author_avatar(
     reply: poise::CreateReply,
-    author_avatar_file: &Option<(PathBuf, String)>,
+    author_avatar_file: &Option<(MediaFile, String)>,
 ) -> poise::CreateReply {
-    if let Some((path, _)) = author_avatar_file {
-        if let Ok(att) = serenity::CreateAttachment::path(path).await {
+    if let Some((file, filename)) = author_avatar_file {
+        if let Some(att) = media_attachment(file, filename).await {
             return reply.attachment(att);
         }
     }
     reply
 }
-async fn send_reply(ctx: Context<'_>, reply: poise::CreateReply) -> Option<serenity::Message> {
-    let msg = ctx.send(reply).await.ok()?.into_message().await.ok()?;
-    time::sleep(MESSAGE_DELAY).await;
-    Some(msg)
+fn reply_to_message(reply: poise::CreateReply) -> serenity::CreateMessage {
+    let mut message = serenity::CreateMessage::new()
+        .embeds(reply.embeds)
+        .add_files(reply.attachments);
+    if let Some(content) = reply.content {
+        message = message.content(content);
+    }
+    if let Some(components) = reply.components {
+        message = message.components(components);
+    }
+    if let Some(allowed_mentions) = reply.allowed_mentions {
+        message = message.allowed_mentions(allowed_mentions);
+    }
+    message
+}
+async fn send_reply(
+    ctx: Context<'_>,
+    reply: poise::CreateReply,
+    delivery: &mut Delivery,
+) -> Option<serenity::Message> {
+    let scheduler = &ctx.data().scheduler;
+    let route = ctx.channel_id().get();
+    let result = if delivery.ephemeral {
+        let reply = reply.ephemeral(true);
+        scheduler
+            .run_once(route, || {
+                let reply = reply.clone();
+                async move { ctx.send(reply).await?.into_message().await }
+            })
+            .await
+    } else {
+        let message = reply_to_message(reply)
+            .nonce(message_nonce())
+            .enforce_nonce(true);
+        scheduler
+            .run(route, || {
+                let message = message.clone();
+                async move { ctx.channel_id().send_message(ctx, message).await }
+            })
+            .await
+    };
+    match result {
+        Ok(msg) => {
+            delivery.sent += 1;
+            Some(msg)
+        }
+        Err(e) => {
+            delivery.errors.push(e);
+            None
+        }
+    }
+}
+fn record_reaction_buttons(
+    ctx: Context<'_>,
+    message: &serenity::Message,
+    button: bool,
+    reactions: &[ReactionInfo],
+    disable_button: bool,
+) {
+    if !button || disable_button || reactions.is_empty() {
+        return;
+    }
+    let entry = StoredReactions {
+        message_id: message.id,
+        reactions: store_reactions(reactions),
+    };
+    let _ = append_reaction_store(REACTION_STORE_PATH, &entry);
+    ctx.data()
+        .reaction_store
+        .lock()
+        .unwrap()
+        .insert(entry.message_id, entry.reactions);
 }
 fn add_embeds_to_reply(
     mut reply: poise::CreateReply,
@@ -202,7 +426,7 @@ fn add_attachments_to_reply(
 async fn prepare_batch(
     images: &[MediaSource],
     base_embed: &serenity::CreateEmbed,
-    author_avatar_file: &Option<(PathBuf, String)>,
+    author_avatar_file: &Option<(MediaFile, String)>,
     is_first_batch: bool,
     content: &str,
     embed_url: &str,
@@ -211,8 +435,8 @@ async fn prepare_batch(
     let mut embeds = Vec::new();
     let mut images_processed = 0;
     if is_first_batch {
-        if let Some((avatar_path, _)) = author_avatar_file {
-            if let Ok(attachment) = serenity::CreateAttachment::path(avatar_path).await {
+        if let Some((avatar_file, avatar_filename)) = author_avatar_file {
+            if let Some(attachment) = media_attachment(avatar_file, avatar_filename).await {
                 attachments.push(attachment);
             }
         }
@@ -221,7 +445,8 @@ async fn prepare_batch(
         if embeds.len() >= MAX_EMBEDS {
             break;
         }
-        if matches!(source, MediaSource::Local(..)) && attachments.len() >= MAX_ATTACHMENTS {
+        let is_upload = matches!(source, MediaSource::Local(..) | MediaSource::Fetched(..));
+        if is_upload && attachments.len() >= MAX_ATTACHMENTS {
             break;
         }
         let mut embed_builder = if images_processed == 0 && is_first_batch {
@@ -234,18 +459,29 @@ async fn prepare_batch(
             serenity::CreateEmbed::new()
         };
         embed_builder = embed_builder.url(embed_url);
+        let unavailable = |embed_builder: serenity::CreateEmbed, filename: &str| {
+            let placeholder = media_unavailable(filename);
+            if images_processed == 0 && is_first_batch && !content.is_empty() {
+                embed_builder.description(format!("{content}\n{placeholder}"))
+            } else {
+                embed_builder.description(placeholder)
+            }
+        };
         match source {
-            MediaSource::Local(path, filename) => {
-                if let Ok(attachment) = serenity::CreateAttachment::path(path).await {
+            MediaSource::Local(_, filename) | MediaSource::Fetched(_, filename) => {
+                if let Some(attachment) = source_attachment(source).await {
                     attachments.push(attachment);
                     embed_builder = embed_builder.image(format!("attachment://{filename}"));
                 } else {
-                    continue;
+                    embed_builder = unavailable(embed_builder, filename);
                 }
             }
             MediaSource::Remote(url) => {
                 embed_builder = embed_builder.image(url.clone());
             }
+            MediaSource::Unavailable(filename) => {
+                embed_builder = unavailable(embed_builder, filename);
+            }
         }
         embeds.push(embed_builder);
         images_processed += 1;
@@ -260,14 +496,11 @@ async fn send_text_message(
     ctx: Context<'_>,
     message: &MessageInfo,
     base_embed: serenity::CreateEmbed,
-    author_avatar_file: &Option<(PathBuf, String)>,
-    no_mentions: bool,
-    button: bool,
-    reaction_users: bool,
-    reactions: &[ReactionInfo],
-    disable_button: bool,
+    author_avatar_file: &Option<(MediaFile, String)>,
+    options: &ImportOptions,
+    delivery: &mut Delivery,
 ) -> Option<serenity::Message> {
-    let content = replace_mentions(&message.content, &message.mentions, no_mentions);
+    let content = replace_mentions(&message.content, &message.mentions, options.no_mentions);
     let content = replace_emojis(&content, &message.inline_emojis);
     if content.is_empty() && author_avatar_file.is_none() {
         return None;
@@ -275,9 +508,11 @@ async fn send_text_message(
     let embed_builder = base_embed.description(&content);
     let reply = poise::CreateReply::default().embed(embed_builder);
     let reply = attach_author_avatar(reply, author_avatar_file).await;
-    let reply = with_reaction_buttons(reply, button, reactions, disable_button);
-    let msg = send_reply(ctx, reply).await?;
-    show_reaction_users(ctx, reaction_users, reactions).await;
+    let reactions = &message.reactions;
+    let reply = with_reaction_buttons(reply, options.button, reactions, options.disable_button);
+    let msg = send_reply(ctx, reply, delivery).await?;
+    record_reaction_buttons(ctx, &msg, options.button, reactions, options.disable_button);
+    show_reaction_users(ctx, options.reaction_users, reactions, delivery).await;
     Some(msg)
 }
 async fn send_image_messages(
@@ -285,15 +520,13 @@ async fn send_image_messages(
     message: &MessageInfo,
     base_embed: serenity::CreateEmbed,
     image_sources: Vec<MediaSource>,
-    author_avatar_file: Option<(PathBuf, String)>,
-    embed_url: String,
-    no_mentions: bool,
-    button: bool,
-    reaction_users: bool,
-    reactions: &[ReactionInfo],
-    disable_button: bool,
+    author_avatar_file: Option<(MediaFile, String)>,
+    options: &ImportOptions,
+    delivery: &mut Delivery,
 ) -> Option<serenity::Message> {
-    let content = replace_mentions(&message.content, &message.mentions, no_mentions);
+    let reactions = &message.reactions;
+    let embed_url = author_url(&message.author);
+    let content = replace_mentions(&message.content, &message.mentions, options.no_mentions);
     let content = replace_emojis(&content, &message.inline_emojis);
     let mut remaining_images: &[MediaSource] = &image_sources;
     let mut is_first_batch = true;
@@ -312,10 +545,21 @@ async fn send_image_messages(
             let mut reply = poise::CreateReply::default();
             reply = add_embeds_to_reply(reply, batch.embeds);
             reply = add_attachments_to_reply(reply, batch.attachments);
-            if remaining_images.len() <= batch.count {
-                reply = with_reaction_buttons(reply, button, reactions, disable_button);
+            let is_last_batch = remaining_images.len() <= batch.count;
+            if is_last_batch {
+                reply =
+                    with_reaction_buttons(reply, options.button, reactions, options.disable_button);
             }
-            if let Some(msg) = send_reply(ctx, reply).await {
+            if let Some(msg) = send_reply(ctx, reply, delivery).await {
+                if is_last_batch {
+                    record_reaction_buttons(
+                        ctx,
+                        &msg,
+                        options.button,
+                        reactions,
+                        options.disable_button,
+                    );
+                }
                 last_msg = Some(msg);
             }
         }
@@ -325,50 +569,52 @@ async fn send_image_messages(
         remaining_images = &remaining_images[batch.count..];
         is_first_batch = false;
     }
-    show_reaction_users(ctx, reaction_users, reactions).await;
+    show_reaction_users(ctx, options.reaction_users, reactions, delivery).await;
     last_msg
 }
 async fn send_attachment_batch(
     ctx: Context<'_>,
     attachments: Vec<serenity::CreateAttachment>,
     content: Option<String>,
-    button: bool,
     reactions: &[ReactionInfo],
-    disable_button: bool,
+    options: &ImportOptions,
+    delivery: &mut Delivery,
 ) -> Option<serenity::Message> {
     let mut reply = poise::CreateReply::default();
     if let Some(c) = content {
         reply = reply.content(c);
     }
     reply = add_attachments_to_reply(reply, attachments);
-    reply = with_reaction_buttons(reply, button, reactions, disable_button);
-    send_reply(ctx, reply).await
+    reply = with_reaction_buttons(reply, options.button, reactions, options.disable_button);
+    let msg = send_reply(ctx, reply, delivery).await?;
+    record_reaction_buttons(ctx, &msg, options.button, reactions, options.disable_button);
+    Some(msg)
 }
 async fn send_outside_message(
     ctx: Context<'_>,
     message: &MessageInfo,
     base_embed: Option<serenity::CreateEmbed>,
     attachment_sources: Vec<MediaSource>,
-    author_avatar_file: Option<(PathBuf, String)>,
-    no_mentions: bool,
-    button: bool,
-    reaction_users: bool,
-    reactions: &[ReactionInfo],
-    disable_button: bool,
+    author_avatar_file: Option<(MediaFile, String)>,
+    options: &ImportOptions,
+    delivery: &mut Delivery,
 ) -> Option<serenity::Message> {
+    let reactions = &message.reactions;
     let mut locals: Vec<serenity::CreateAttachment> = Vec::new();
     let mut remotes: Vec<String> = Vec::new();
     for source in attachment_sources {
         match source {
-            MediaSource::Local(path, _) => {
-                if let Ok(attachment) = serenity::CreateAttachment::path(&path).await {
-                    locals.push(attachment);
+            MediaSource::Remote(url) => remotes.push(url),
+            MediaSource::Unavailable(filename) => remotes.push(media_unavailable(&filename)),
+            MediaSource::Local(_, ref filename) | MediaSource::Fetched(_, ref filename) => {
+                match source_attachment(&source).await {
+                    Some(attachment) => locals.push(attachment),
+                    None => remotes.push(media_unavailable(filename)),
                 }
             }
-            MediaSource::Remote(url) => remotes.push(url),
         }
     }
-    let mut content = replace_mentions(&message.content, &message.mentions, no_mentions);
+    let mut content = replace_mentions(&message.content, &message.mentions, options.no_mentions);
     content = replace_emojis(&content, &message.inline_emojis);
     if !remotes.is_empty() {
         if !content.is_empty() {
@@ -376,11 +622,22 @@ async fn send_outside_message(
         }
         content.push_str(&remotes.join("\n"));
     }
+    let reaction_summary = if options.no_embed && options.reaction_counts {
+        format_reaction_summary(reactions)
+    } else {
+        String::new()
+    };
+    if !reaction_summary.is_empty() {
+        if !content.is_empty() {
+            content.push('\n');
+        }
+        content.push_str(&reaction_summary);
+    }
     let mut last_attachment_msg: Option<serenity::Message> = None;
     if let Some(embed) = base_embed {
         let reply = poise::CreateReply::default().embed(embed);
         let reply = attach_author_avatar(reply, &author_avatar_file).await;
-        if let Some(metadata_msg) = send_reply(ctx, reply).await {
+        if let Some(metadata_msg) = send_reply(ctx, reply, delivery).await {
             last_attachment_msg = Some(metadata_msg);
         }
     }
@@ -395,8 +652,7 @@ async fn send_outside_message(
         let batch: Vec<serenity::CreateAttachment> =
             remaining_locals.drain(0..batch_size).collect();
         if let Some(msg) =
-            send_attachment_batch(ctx, batch, batch_content, button, reactions, disable_button)
-                .await
+            send_attachment_batch(ctx, batch, batch_content, reactions, options, delivery).await
         {
             last_attachment_msg = Some(msg);
         }
@@ -405,26 +661,51 @@ async fn send_outside_message(
             let batch: Vec<serenity::CreateAttachment> =
                 remaining_locals.drain(0..batch_size).collect();
             if let Some(msg) =
-                send_attachment_batch(ctx, batch, None, button, reactions, disable_button).await
+                send_attachment_batch(ctx, batch, None, reactions, options, delivery).await
             {
                 last_attachment_msg = Some(msg);
             }
         }
     }
-    show_reaction_users(ctx, reaction_users, reactions).await;
+    show_reaction_users(ctx, options.reaction_users, reactions, delivery).await;
     last_attachment_msg
 }
-async fn add_reactions(ctx: Context<'_>, message: &serenity::Message, reactions: &[ReactionInfo]) {
+async fn add_reactions(
+    ctx: Context<'_>,
+    message: &serenity::Message,
+    reactions: &[ReactionInfo],
+    delivery: &mut Delivery,
+) {
     let reaction_types = create_reactions(reactions);
     for reaction_type in reaction_types {
-        let _ = message.react(&ctx, reaction_type).await;
-        time::sleep(MESSAGE_DELAY).await;
+        let result = ctx
+            .data()
+            .scheduler
+            .run(message.channel_id.get(), || {
+                message.react(ctx, reaction_type.clone())
+            })
+            .await;
+        if let Err(e) = result {
+            delivery
+                .errors
+                .push(format!("Reaction {reaction_type}: {e}"));
+        }
     }
 }
 async fn fetch_current_avatar_url(ctx: &Context<'_>, user_id: serenity::UserId) -> Option<String> {
     let user = user_id.to_user(ctx).await.ok()?;
     Some(user.face())
 }
+async fn fetch_guild_emojis(ctx: &Context<'_>) -> HashSet<String> {
+    let Some(guild_id) = ctx.guild_id() else {
+        return HashSet::new();
+    };
+    guild_id
+        .emojis(ctx)
+        .await
+        .map(|emojis| emojis.iter().map(|emoji| emoji.id.to_string()).collect())
+        .unwrap_or_default()
+}
 async fn fetch_accent_color(ctx: &Context<'_>, user_id: serenity::UserId) -> Option<u32> {
     let user = user_id.to_user(ctx).await.ok()?;
     user.accent_colour.map(|color| color.0)
@@ -434,41 +715,53 @@ async fn process_message(
     message: &MessageInfo,
     export: &Export,
     file_index: &Option<FileIndex>,
-    seen_paths: &mut HashSet<PathBuf>,
+    ambiguous_media: &mut Vec<String>,
     options: &ImportOptions,
-) {
+) -> Delivery {
+    let mut delivery = Delivery {
+        ephemeral: options.dry_run,
+        ..Default::default()
+    };
     let author_avatar_file = if options.no_embed || options.current_avatar {
         None
     } else {
         file_index
             .as_ref()
-            .and_then(|index| find_avatar(&message.author.id, index))
+            .and_then(|index| find_avatar(&message.author, index))
     };
-    let current_avatar_url = if options.current_avatar {
-        fetch_current_avatar_url(&ctx, message.author.id).await
-    } else {
-        None
+    let current_avatar_url = match message.author.id {
+        Some(author_id) if options.current_avatar => {
+            fetch_current_avatar_url(&ctx, author_id).await
+        }
+        _ => None,
     };
-    let accent_color_value = if options.accent_color {
-        fetch_accent_color(&ctx, message.author.id).await
+    let accent_color_value = match message.author.id {
+        Some(author_id) if options.accent_color => fetch_accent_color(&ctx, author_id).await,
+        _ => None,
+    };
+    let reaction_summary = if options.reaction_counts {
+        Some(format_reaction_summary(&message.reactions)).filter(|s| !s.is_empty())
     } else {
         None
     };
     let last_sent_message = if options.outside {
-        let attachment_sources = collect_sources(message, file_index, seen_paths, |_| true);
+        let attachment_sources = collect_sources(message, file_index, ambiguous_media, |_| true);
+        let attachment_sources =
+            refresh_expired_sources(ctx.data(), ctx.http().token(), attachment_sources).await;
+        note_media(&mut delivery, &attachment_sources);
         let base_embed = if options.no_embed {
             None
         } else {
-            Some(create_embed_base(
-                message,
-                export,
-                author_avatar_file.as_ref().map(|(_, name)| name),
-                current_avatar_url.as_deref(),
-                options.no_guild,
-                options.no_category,
-                options.no_channel,
-                options.no_timestamp,
-                accent_color_value,
+            Some(with_reaction_summary(
+                create_embed_base(
+                    message,
+                    export,
+                    author_avatar_file.as_ref().map(|(_, name)| name),
+                    current_avatar_url.as_deref(),
+                    options,
+                    accent_color_value,
+                ),
+                reaction_summary.as_deref(),
             ))
         };
         send_outside_message(
@@ -477,27 +770,27 @@ async fn process_message(
             base_embed,
             attachment_sources,
             author_avatar_file,
-            options.no_mentions,
-            options.button,
-            options.reaction_users,
-            &message.reactions,
-            options.disable_button,
+            options,
+            &mut delivery,
         )
         .await
     } else {
-        let image_sources = collect_sources(message, file_index, seen_paths, |att| {
+        let image_sources = collect_sources(message, file_index, ambiguous_media, |att| {
             is_image_file(&att.file_name)
         });
-        let base_embed = create_embed_base(
-            message,
-            export,
-            author_avatar_file.as_ref().map(|(_, name)| name),
-            current_avatar_url.as_deref(),
-            options.no_guild,
-            options.no_category,
-            options.no_channel,
-            options.no_timestamp,
-            accent_color_value,
+        let image_sources =
+            refresh_expired_sources(ctx.data(), ctx.http().token(), image_sources).await;
+        note_media(&mut delivery, &image_sources);
+        let base_embed = with_reaction_summary(
+            create_embed_base(
+                message,
+                export,
+                author_avatar_file.as_ref().map(|(_, name)| name),
+                current_avatar_url.as_deref(),
+                options,
+                accent_color_value,
+            ),
+            reaction_summary.as_deref(),
         );
         if image_sources.is_empty() {
             send_text_message(
@@ -505,45 +798,115 @@ async fn process_message(
                 message,
                 base_embed,
                 &author_avatar_file,
-                options.no_mentions,
-                options.button,
-                options.reaction_users,
-                &message.reactions,
-                options.disable_button,
+                options,
+                &mut delivery,
             )
             .await
         } else {
-            let author_id = message.author.id;
-            let embed_url = user_profile_url(author_id);
             send_image_messages(
                 ctx,
                 message,
                 base_embed,
                 image_sources,
                 author_avatar_file,
-                embed_url,
-                options.no_mentions,
-                options.button,
-                options.reaction_users,
-                &message.reactions,
-                options.disable_button,
+                options,
+                &mut delivery,
             )
             .await
         }
     };
     if let Some(sent_msg) = last_sent_message {
-        if !options.button && !options.no_reactions && !message.reactions.is_empty() {
-            add_reactions(ctx, &sent_msg, &message.reactions).await;
+        if !options.button
+            && !options.no_reactions
+            && !options.dry_run
+            && !message.reactions.is_empty()
+        {
+            add_reactions(ctx, &sent_msg, &message.reactions, &mut delivery).await;
+        }
+    }
+    delivery
+}
+async fn wait_for_queue(
+    ctx: Context<'_>,
+    job_id: u64,
+    start_at: Option<serenity::Timestamp>,
+) -> bool {
+    let mut announced = false;
+    while job_state(ctx.data(), job_id) == Some(JobState::Queued) {
+        if try_start_job(ctx.data(), job_id) {
+            return true;
         }
+        if !announced {
+            let announcement = match (queue_position(ctx.data(), job_id), start_at) {
+                (0, Some(start_at)) => format!(
+                    "Scheduled job #{job_id} for <t:{}:f>...",
+                    start_at.unix_timestamp()
+                ),
+                (position, _) => format!("Queued as job #{job_id} at position {position}..."),
+            };
+            let _ = ctx.say(announcement).await;
+            announced = true;
+        }
+        time::sleep(QUEUE_POLL_INTERVAL).await;
     }
+    false
+}
+async fn fail_job(ctx: Context<'_>, job_id: u64, error: String) -> Result<(), Error> {
+    finish_job(ctx.data(), job_id, JobState::Failed, Some(error.clone()));
+    let _ = ctx.say(error).await;
+    Ok(())
 }
-#[poise::command(prefix_command)]
-pub async fn import(ctx: Context<'_>, #[rest] args: String) -> Result<(), Error> {
-    let argument_tokens = split_args(&args);
-    if argument_tokens.is_empty() || argument_tokens[0].trim().is_empty() {
+/// Imports messages from an export file, optionally uploaded with its media ZIP.
+#[poise::command(prefix_command, slash_command, check = "check_access")]
+pub async fn import(
+    ctx: Context<'_>,
+    #[description = "Export file to import instead of a path or link."] export: Option<
+        serenity::Attachment,
+    >,
+    #[description = "Media ZIP to use instead of a path or link."] media: Option<
+        serenity::Attachment,
+    >,
+    #[rest]
+    #[description = "Path or link to the export and media, followed by options."]
+    args: Option<String>,
+) -> Result<(), Error> {
+    ctx.defer().await?;
+    let mut argument_tokens = split_args(args.as_deref().unwrap_or(""));
+    if let Some(export) = &export {
+        argument_tokens.insert(0, export.url.clone());
+    }
+    if argument_tokens
+        .first()
+        .is_none_or(|path| path.trim().is_empty() || path.starts_with("--"))
+    {
         ctx.say("Command requires a path to a JSON file.").await?;
         return Ok(());
     }
+    if let Some(media) = &media {
+        argument_tokens.insert(1, media.url.clone());
+    }
+    run_import(ctx, argument_tokens, None).await
+}
+/// Resumes an import job interrupted by a restart.
+#[poise::command(prefix_command, slash_command, check = "check_access")]
+pub async fn resume(
+    ctx: Context<'_>,
+    #[description = "ID of the job to resume."] id: u64,
+) -> Result<(), Error> {
+    ctx.defer().await?;
+    match requeue_job(ctx.data(), id, ctx.channel_id()) {
+        Ok((argument_tokens, done)) => run_import(ctx, argument_tokens, Some((id, done))).await,
+        Err(e) => {
+            ctx.say(e).await?;
+            Ok(())
+        }
+    }
+}
+async fn run_import(
+    ctx: Context<'_>,
+    argument_tokens: Vec<String>,
+    resumed_job: Option<(u64, usize)>,
+) -> Result<(), Error> {
     let json_path = argument_tokens[0].clone();
     let (media_path, options_tokens) = if argument_tokens.len() > 1 {
         let next_str = &argument_tokens[1];
@@ -555,90 +918,494 @@ pub async fn import(ctx: Context<'_>, #[rest] args: String) -> Result<(), Error>
     } else {
         (None, &argument_tokens[0..0])
     };
-    let options = match parse_options(options_tokens) {
+    let options = parse_options(options_tokens)
+        .map_err(|e| format!("Error parsing options: {e}"))
+        .and_then(|options| {
+            check_input(&json_path, ctx.guild_id())?;
+            if let Some(media_path) = &media_path {
+                check_input(media_path, ctx.guild_id())?;
+            }
+            Ok(options)
+        });
+    let options = match options {
         Ok(opts) => opts,
         Err(e) => {
-            ctx.say(format!("Error parsing options: {e}")).await?;
+            if let Some((job_id, _)) = resumed_job {
+                return fail_job(ctx, job_id, e).await;
+            }
+            ctx.say(e).await?;
             return Ok(());
         }
     };
-    let export = match load_export(&json_path).await {
-        Ok(data) => data,
-        Err(e) => {
-            let _ = ctx.say(e).await;
-            return Ok(());
+    let json_path = refresh_cdn_url(ctx.data(), ctx.http().token(), json_path).await;
+    let media_path = match media_path {
+        Some(media_path) => Some(refresh_cdn_url(ctx.data(), ctx.http().token(), media_path).await),
+        None => None,
+    };
+    let (job_id, skipped) = match resumed_job {
+        Some(resumed_job) => resumed_job,
+        None => {
+            let job_id = create_job(
+                ctx.data(),
+                ctx.author().id,
+                ctx.guild_id(),
+                ctx.channel_id(),
+                &argument_tokens,
+                &options,
+            );
+            (job_id, 0)
         }
     };
-    let messages_to_process = select_messages(
-        &export.messages,
-        options.range_start,
-        options.range_end,
-        options.first,
-        options.last,
-    );
-    if messages_to_process.is_empty() {
-        ctx.say("No messages to import.").await?;
+    if !wait_for_queue(ctx, job_id, options.start_at).await {
+        let _ = ctx.say(format!("Job #{job_id} cancelled")).await;
         return Ok(());
     }
-    let _ = ctx
-        .say(format!(
-            "Importing {} messages...",
-            messages_to_process.len()
-        ))
-        .await?;
-    let (file_index, _tempdir_guard) = create_file_index(&media_path, &json_path).await;
-    let mut seen_paths = HashSet::new();
-    set_cancellation(&ctx, false);
+    let (progress_sender, progress_receiver) = watch::channel(None);
+    let progress_reporter = tokio::spawn(report_download_progress(
+        ctx.serenity_context().http.clone(),
+        ctx.channel_id(),
+        progress_receiver,
+    ));
+    let export_files =
+        match resolve_export_files(&ctx.data().http_client, &json_path, Some(&progress_sender))
+            .await
+        {
+            Ok(file) => file,
+            Err(e) => return fail_job(ctx, job_id, e).await,
+        };
+    let message_count = match count_messages(&export_files.paths, &options) {
+        Ok(count) => count,
+        Err(e) => return fail_job(ctx, job_id, e).await,
+    };
+    if message_count == Some(0) {
+        finish_job(ctx.data(), job_id, JobState::Done, None);
+        let _ = ctx.say("No messages to import.").await;
+        return Ok(());
+    }
+    let mut stream = match open_export(&export_files.paths, options.source_channel.as_deref()).await
+    {
+        Ok(stream) => stream,
+        Err(e) => return fail_job(ctx, job_id, e).await,
+    };
+    if let Some(guild_name) = &options.guild_name {
+        stream.export.guild.name = guild_name.clone();
+    }
+    if let Some(channel_name) = &options.channel_name {
+        stream.export.channel.name = channel_name.clone();
+    }
+    let action = if options.dry_run {
+        "Previewing"
+    } else {
+        "Importing"
+    };
+    let status = job_status(job_id, action);
+    let announcement = match (skipped, message_count) {
+        (0, Some(count)) => format!("{action} {count} messages as job #{job_id}..."),
+        (0, None) => format!("{action} messages as job #{job_id}..."),
+        (_, Some(count)) => format!(
+            "Resuming job #{job_id} at message {} of {count}...",
+            skipped + 1
+        ),
+        (_, None) => format!("Resuming job #{job_id} at message {}...", skipped + 1),
+    };
+    let _ = ctx.say(announcement).await;
+    let mut progress = ImportProgress::new(message_count);
+    progress.skipped = skipped;
+    update_job_progress(ctx.data(), job_id, &progress);
+    let mut progress_message = ctx
+        .channel_id()
+        .send_message(
+            ctx,
+            serenity::CreateMessage::new()
+                .content(format_progress(&progress, &status))
+                .components(progress_buttons(job_id, JobState::Running)),
+        )
+        .await
+        .ok();
+    let mut last_update = Instant::now();
+    let media_path = media_path.or_else(|| {
+        (!is_url(&json_path))
+            .then(|| default_media_dir(&export_files.paths[0]))
+            .flatten()
+    });
+    let (file_index, _tempdir_guard) = create_file_index(
+        &ctx.data().http_client,
+        &media_path,
+        &export_files.name,
+        Some(&progress_sender),
+    )
+    .await;
+    drop(progress_sender);
+    let _ = progress_reporter.await;
+    let mut ambiguous_media = Vec::new();
+    let guild_emojis = if options.dry_run {
+        fetch_guild_emojis(&ctx).await
+    } else {
+        HashSet::new()
+    };
+    let mut dry_run_report = DryRunReport::default();
+    let can_preview = matches!(ctx, poise::Context::Application(_));
+    let mut summary = ImportSummary::default();
+    let mut selector = MessageSelector::new(&options);
     let mut cancelled = false;
-    for message in messages_to_process {
-        if is_cancelled(&ctx) {
-            cancelled = true;
-            break;
-        }
-        process_message(
+    let mut failure = None;
+    let mut last_send = None;
+    let mut upcoming = VecDeque::new();
+    let needs_refresh = |attachment: &AttachmentInfo| {
+        (options.outside || is_image_file(&attachment.file_name))
+            && !file_index.as_ref().is_some_and(|index| {
+                let id = Some(attachment.id.as_str()).filter(|id| !id.is_empty());
+                matches!(
+                    resolve_media(&attachment.url, id, &attachment.file_name, index),
+                    MediaMatch::Found(_)
+                )
+            })
+    };
+    loop {
+        let next = next_upcoming(
             ctx,
-            message,
-            &export,
-            &file_index,
-            &mut seen_paths,
-            &options,
+            &mut selector,
+            &mut stream.messages,
+            &mut upcoming,
+            progress.skipped.saturating_sub(progress.done),
+            (!options.dry_run).then_some(&needs_refresh),
         )
         .await;
+        let message = match next {
+            Ok(Some(message)) => message,
+            Ok(None) => break,
+            Err(e) => {
+                failure = Some(e);
+                break;
+            }
+        };
+        if progress.done < progress.skipped {
+            progress.advance(&message, 0);
+            continue;
+        }
+        if !options.dry_run
+            && wait_for_schedule(
+                ctx,
+                job_id,
+                &options,
+                &mut progress_message,
+                &mut progress,
+                last_send,
+            )
+            .await
+                == JobState::Cancelled
+        {
+            cancelled = true;
+            break;
+        }
+        if wait_while_paused(ctx, job_id, &mut progress_message, &mut progress).await
+            == JobState::Cancelled
+        {
+            cancelled = true;
+            break;
+        }
+        last_send = Some(Instant::now());
+        if options.dry_run {
+            if can_preview && dry_run_report.messages < DRY_RUN_PREVIEW_COUNT {
+                let mut preview_ambiguous = Vec::new();
+                process_message(
+                    ctx,
+                    &message,
+                    &stream.export,
+                    &file_index,
+                    &mut preview_ambiguous,
+                    &options,
+                )
+                .await;
+            }
+            record_message(
+                &mut dry_run_report,
+                &message,
+                &file_index,
+                &mut ambiguous_media,
+                &guild_emojis,
+                &options,
+            );
+        } else {
+            let delivery = process_message(
+                ctx,
+                &message,
+                &stream.export,
+                &file_index,
+                &mut ambiguous_media,
+                &options,
+            )
+            .await;
+            record_outcome(&mut summary, &message, delivery);
+        }
+        progress.advance(&message, summary.failed + summary.partial);
+        update_job_progress(ctx.data(), job_id, &progress);
+        if last_update.elapsed() >= PROGRESS_INTERVAL {
+            save_jobs(ctx.data());
+            let buttons = progress_buttons(job_id, JobState::Running);
+            update_progress(ctx, &mut progress_message, &progress, &status, buttons).await;
+            last_update = Instant::now();
+        }
     }
-    remove_cancellation(&ctx);
+    let (state, status) = if cancelled {
+        (JobState::Cancelled, "Cancelled")
+    } else if failure.is_some() {
+        (JobState::Failed, "Stopped")
+    } else {
+        (JobState::Done, "Finished")
+    };
+    if state == JobState::Done {
+        progress.total.get_or_insert(progress.done);
+        update_job_progress(ctx.data(), job_id, &progress);
+    }
+    finish_job(ctx.data(), job_id, state, failure.clone());
+    let status = job_status(job_id, status);
+    update_progress(ctx, &mut progress_message, &progress, &status, Vec::new()).await;
     let message = if cancelled {
         "Import cancelled".to_string()
+    } else if let Some(e) = failure {
+        format!("Import stopped: {e}")
+    } else if progress.done == 0 {
+        "No messages to import.".to_string()
+    } else if options.dry_run {
+        let mut report = format_dry_run_report(&dry_run_report, ambiguous_media.len());
+        if !can_preview {
+            report.push_str("\nMessage previews are only shown for the slash command, where they can be hidden.");
+        }
+        report
     } else {
         build_completion_message(
-            &export,
+            &stream.export,
             options.no_guild,
             options.no_category,
             options.no_channel,
         )
     };
-    let _ = ctx.say(message).await?;
+    let mut reply = poise::CreateReply::default().content(message);
+    if !options.dry_run {
+        reply = reply.embed(summary_embed(&summary));
+        if let Some(attachment) = report_attachment(&summary) {
+            reply = reply.attachment(attachment);
+        }
+    }
+    let _ = send_reply(ctx, reply, &mut Delivery::default()).await;
+    if !ambiguous_media.is_empty() {
+        let report = format_ambiguous_media(&ambiguous_media);
+        let _ = send_reply(
+            ctx,
+            poise::CreateReply::default().content(report),
+            &mut Delivery::default(),
+        )
+        .await;
+    }
     Ok(())
 }
-/// Cancels the ongoing import in the current channel.
-#[poise::command(prefix_command, slash_command)]
-pub async fn cancel(
+async fn next_upcoming(
     ctx: Context<'_>,
-    #[description = "Hide messages of command output from other users."] ephemeral: bool,
+    selector: &mut MessageSelector<'_>,
+    stream: &mut MessageStream,
+    upcoming: &mut VecDeque<Result<MessageInfo, String>>,
+    skip: usize,
+    refresh: Option<&impl Fn(&AttachmentInfo) -> bool>,
+) -> Result<Option<MessageInfo>, String> {
+    if upcoming.is_empty() {
+        while upcoming.len() < REFRESH_LOOKAHEAD {
+            match selector.next(stream).await {
+                Ok(Some(message)) => upcoming.push_back(Ok(message)),
+                Ok(None) => break,
+                Err(e) => {
+                    upcoming.push_back(Err(e));
+                    break;
+                }
+            }
+        }
+        if let Some(filter) = refresh {
+            let messages = upcoming
+                .iter_mut()
+                .skip(skip)
+                .filter_map(|message| message.as_mut().ok());
+            refresh_upcoming_urls(ctx.data(), ctx.http().token(), messages, filter).await;
+        }
+    }
+    upcoming.pop_front().transpose()
+}
+fn format_download_progress(progress: &DownloadProgress) -> String {
+    match progress.total {
+        Some(total) if total > 0 => format!(
+            "Downloading {}: {} / {} ({}%)",
+            progress.kind,
+            format_size(progress.downloaded),
+            format_size(total),
+            progress.downloaded * 100 / total
+        ),
+        _ => format!(
+            "Downloading {}: {}",
+            progress.kind,
+            format_size(progress.downloaded)
+        ),
+    }
+}
+async fn report_download_progress(
+    http: Arc<serenity::Http>,
+    channel_id: serenity::ChannelId,
+    mut receiver: watch::Receiver<Option<DownloadProgress>>,
+) {
+    let mut status: Option<serenity::Message> = None;
+    loop {
+        let finished = receiver.changed().await.is_err();
+        let Some(progress) = receiver.borrow_and_update().clone() else {
+            if finished {
+                break;
+            }
+            continue;
+        };
+        let content = format_download_progress(&progress);
+        match &mut status {
+            Some(message) => {
+                let _ = message
+                    .edit(&http, EditMessage::new().content(content))
+                    .await;
+            }
+            None => status = channel_id.say(&http, content).await.ok(),
+        }
+        if finished {
+            break;
+        }
+        time::sleep(PROGRESS_INTERVAL).await;
+    }
+}
+async fn respond_reaction_users(
+    ctx: &serenity::Context,
+    interaction: &serenity::ComponentInteraction,
+    data: &Data,
+    button_key: &str,
+) -> Result<(), Error> {
+    let content = {
+        let store = data.reaction_store.lock().unwrap();
+        store.get(&interaction.message.id).and_then(|reactions| {
+            if button_key == MORE_REACTIONS_KEY {
+                let visible = visible_button_count(reactions.len());
+                Some(format_more_reactions(&reactions[visible..]))
+            } else {
+                button_key
+                    .parse::<usize>()
+                    .ok()
+                    .and_then(|index| reactions.get(index))
+                    .map(format_stored_reaction)
+            }
+        })
+    }
+    .unwrap_or_else(|| "No reaction data is stored for this button.".to_string());
+    let response = serenity::CreateInteractionResponseMessage::new()
+        .content(content)
+        .allowed_mentions(serenity::CreateAllowedMentions::new())
+        .ephemeral(true);
+    interaction
+        .create_response(ctx, serenity::CreateInteractionResponse::Message(response))
+        .await?;
+    Ok(())
+}
+async fn respond_import_control(
+    ctx: &serenity::Context,
+    interaction: &serenity::ComponentInteraction,
+    data: &Data,
+    owners: &HashSet<serenity::UserId>,
+    state: JobState,
+    job_id: u64,
 ) -> Result<(), Error> {
-    let should_cancel;
+    let response = if !interaction_allowed(interaction, owners) {
+        serenity::CreateInteractionResponse::Message(
+            serenity::CreateInteractionResponseMessage::new()
+                .content("You are not allowed to manage imports here.")
+                .ephemeral(true),
+        )
+    } else if request_job_state(data, job_id, state) {
+        let status = match state {
+            JobState::Running => "Resuming",
+            JobState::Paused => "Pausing after the current message",
+            _ => "Cancelling",
+        };
+        let message = serenity::CreateInteractionResponseMessage::new()
+            .components(progress_buttons(job_id, state))
+            .content(format!(
+                "**{}**\n{}",
+                job_status(job_id, status),
+                interaction
+                    .message
+                    .content
+                    .split_once('\n')
+                    .map_or("", |(_, rest)| rest)
+            ));
+        serenity::CreateInteractionResponse::UpdateMessage(message)
+    } else {
+        serenity::CreateInteractionResponse::Message(
+            serenity::CreateInteractionResponseMessage::new()
+                .content("This import is no longer running.")
+                .ephemeral(true),
+        )
+    };
+    interaction.create_response(ctx, response).await?;
+    Ok(())
+}
+pub async fn event_handler(
+    ctx: &serenity::Context,
+    event: &serenity::FullEvent,
+    framework: poise::FrameworkContext<'_, Data, Error>,
+    data: &Data,
+) -> Result<(), Error> {
+    if let serenity::FullEvent::Ratelimit { data: info } = event {
+        data.scheduler.note_rate_limit(info);
+    }
+    if let serenity::FullEvent::InteractionCreate {
+        interaction: serenity::Interaction::Component(component),
+    } = event
     {
-        let mut lock = ctx.data().cancellation_flags.lock().unwrap();
-        if lock.contains_key(&ctx.channel_id()) {
-            lock.insert(ctx.channel_id(), true);
-            should_cancel = true;
-        } else {
-            should_cancel = false;
+        let custom_id = component.data.custom_id.as_str();
+        if let Some(button_key) = custom_id
+            .strip_prefix(REACTION_BUTTON_PREFIX)
+            .or_else(|| custom_id.strip_prefix(LEGACY_REACTION_BUTTON_PREFIX))
+        {
+            respond_reaction_users(ctx, component, data, button_key).await?;
+        } else if let Some((state, job_id)) = parse_import_control(custom_id) {
+            let owners = &framework.options().owners;
+            respond_import_control(ctx, component, data, owners, state, job_id).await?;
         }
     }
-    let message = if should_cancel {
-        "Cancelling import..."
-    } else {
-        "No ongoing import in this channel."
+    Ok(())
+}
+/// Cancels an import job, or the ongoing import in the current channel.
+#[poise::command(prefix_command, slash_command, check = "check_access")]
+pub async fn cancel(
+    ctx: Context<'_>,
+    #[description = "ID of the job to cancel."] id: Option<u64>,
+    #[description = "Hide messages of command output from other users."] ephemeral: bool,
+) -> Result<(), Error> {
+    let job_ids = match id {
+        Some(id) if !job_visible(ctx.data(), id, ctx.guild_id(), ctx.author().id) => {
+            ctx.send(
+                poise::CreateReply::default()
+                    .content(format!("No job #{id}."))
+                    .ephemeral(ephemeral),
+            )
+            .await?;
+            return Ok(());
+        }
+        Some(id) => vec![id],
+        None => channel_jobs(ctx.data(), ctx.channel_id()),
+    };
+    let message = match job_ids.as_slice() {
+        [] => "No ongoing import in this channel.".to_string(),
+        [id] if request_job_state(ctx.data(), *id, JobState::Cancelled) => {
+            format!("Cancelling job #{id}...")
+        }
+        [id] => format!("Job #{id} is not running."),
+        ids => format!(
+            "Several imports are running in this channel, pick one with `/cancel <id>`: {}",
+            ids.iter()
+                .map(|id| format!("#{id}"))
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
     };
     ctx.send(
         poise::CreateReply::default()
@@ -648,6 +1415,39 @@ pub async fn cancel(
     .await?;
     Ok(())
 }
+/// Lists import jobs in this server.
+#[poise::command(prefix_command, slash_command, check = "check_access")]
+pub async fn jobs(
+    ctx: Context<'_>,
+    #[description = "Hide messages of command output from other users."] ephemeral: bool,
+) -> Result<(), Error> {
+    ctx.send(
+        poise::CreateReply::default()
+            .content(format_jobs(ctx.data(), ctx.guild_id(), ctx.author().id))
+            .allowed_mentions(serenity::CreateAllowedMentions::new())
+            .ephemeral(ephemeral),
+    )
+    .await?;
+    Ok(())
+}
+/// Shows the details of an import job.
+#[poise::command(prefix_command, slash_command, check = "check_access")]
+pub async fn status(
+    ctx: Context<'_>,
+    #[description = "ID of the job."] id: u64,
+    #[description = "Hide messages of command output from other users."] ephemeral: bool,
+) -> Result<(), Error> {
+    let message = format_job_status(ctx.data(), id, ctx.guild_id(), ctx.author().id)
+        .unwrap_or_else(|| format!("No job #{id}."));
+    ctx.send(
+        poise::CreateReply::default()
+            .content(message)
+            .allowed_mentions(serenity::CreateAllowedMentions::new())
+            .ephemeral(ephemeral),
+    )
+    .await?;
+    Ok(())
+}
 /// Shows Dimport help message.
 #[poise::command(slash_command)]
 pub async fn help(
@@ -657,9 +1457,10 @@ pub async fn help(
     let help_text = r#"
 # Dimport
 `/import <json_path> <media_path> [options]`
+The export and the media ZIP can also be uploaded with the `export` and `media` attachments of the slash command, or attached to the prefix command message, in which case their paths are omitted.
 Imports messages from JSON files generated by [DiscordChatExporter](https://github.com/Tyrrrz/DiscordChatExporter) and replaces expired links with media files downloaded by [Dimage](https://github.com/Inc44/Dimage).
-- `<json_path>`: Path to the DiscordChatExporter JSON file (required).
-- `<media_path>`: Path to the directory containing downloaded media files (optional).
+- `<json_path>`: Path to the DiscordChatExporter JSON, HTML, CSV or TXT file, Slack export ZIP, Telegram `result.json` or Matrix/Element JSON (required). Partitioned exports are chained from the first part or a glob like `"name*.json"`.
+- `<media_path>`: Path to the directory or archive (ZIP, tar, tar.gz, tar.xz, tar.zst, tar.bz2 or 7z) containing downloaded media files (optional).
 
 Options:
 - `--no-guild`: Hide guild/server name from message footer.
@@ -669,36 +1470,70 @@ Options:
 - `--no-mentions`: Skip converting @mentions to clickable Discord mentions.
 - `--no-reactions`: Skip importing reactions entirely.
 - `--no-embed`: Skip creating embeds (only works with `--outside`).
-- `--button`: Display reactions as interactive buttons instead of native Discord reactions.
+- `--button`: Display reactions as buttons that show who reacted when clicked, instead of native Discord reactions.
 - `--reaction-users`: Show detailed list of users who reacted to each message.
 - `--outside`: Send metadata embed separately from attachments.
 - `--disable-button`: Make reaction buttons unclickable (only works with `--button`).
 - `--accent-color`: Use user accent color for embed color instead of exported role color.
 - `--current-avatar`: Use the user's current Discord avatar as author icon.
+- `--reaction-counts`: Add exported reaction counts next to native Discord reactions.
 - `--range <start,end>`: Import messages within specified range (zero-indexed).
 - `--range-start <n>`: Set starting message index for import range.
 - `--range-end <n>`: Set ending message index for import range.
 - `--first <n>`: Import only the first N messages.
 - `--last <n>`: Import only the last N messages.
+- `--source-channel <name>`: Slack channel or Telegram chat to import from exports with several.
+- `--guild-name <name>`: Guild name shown in the footer, for exports without one (CSV).
+- `--channel-name <name>`: Channel name shown in the footer instead of the exported one.
+- `--from-id <message_id>`: Start importing at this exported message.
+- `--to-id <message_id>`: Stop importing at this exported message.
+- `--from-time <time|message_id>`: Import only messages sent at or after this RFC 3339 time or snowflake.
+- `--to-time <time|message_id>`: Import only messages sent at or before this RFC 3339 time or snowflake.
+- `--author <id|name>`: Import only messages from this author (repeatable).
+- `--exclude-author <id|name>`: Skip messages from this author (repeatable).
+- `--after <date|message_id>`: Import only messages sent after this date or message.
+- `--before <date|message_id>`: Import only messages sent before this date or message.
+- `--contains <regex>`: Import only messages whose content matches the regex.
+- `--has <attachment|image|reaction>`: Import only messages with this content (repeatable).
+- `--skip-bots`: Skip messages sent by bots.
+- `--dry-run`: Report what would be imported and privately preview the first messages (slash command only) without importing.
+- `--priority <n>`: Start this import before queued imports with a lower priority (default 0).
+- `--start-at <time>`: Wait in the queue until this RFC 3339 time or date.
+- `--messages-per-minute <n>`: Import at most N messages per minute (up to 6000).
+- `--quiet-hours <start-end>`: Pause between these UTC hours, like `22-8`.
+Filters are applied before range selection, so indexes count filtered messages.
 
-`/cancel [--ephemeral]`
-- Cancels the ongoing import in the current channel.
+`/jobs [--ephemeral]`
+- Lists import jobs in this server with their state, progress, owner and channel.
+
+`/status <id> [--ephemeral]`
+- Shows the details of an import job.
+
+`/resume <id>`
+- Resumes an import job interrupted by a restart from the message where it stopped.
+
+`/cancel [id] [--ephemeral]`
+- Cancels the import job with this ID, or the ongoing import in the current channel.
 
 `/help [--ephemeral]`
 - Shows this help message.
 
+`/import`, `/resume`, `/cancel`, `/jobs` and `/status` require the Manage Server permission unless the bot is configured otherwise, and only the bot owner can use them in direct messages. Local paths only work in the directories the bot allows.
+
 For more details, see the project [README](https://github.com/Inc44/Dimport/blob/master/README.md) or [Wiki](https://github.com/Inc44/Dimport/wiki)."#;
-    let handle = ctx
-        .send(
-            poise::CreateReply::default()
-                .content(help_text)
-                .ephemeral(ephemeral),
-        )
-        .await?;
-    if let Ok(mut msg) = handle.into_message().await {
-        let _ = msg
-            .edit(&ctx, EditMessage::new().suppress_embeds(true))
-            .await;
+    for chunk in split_message(help_text) {
+        let handle = ctx
+            .send(
+                poise::CreateReply::default()
+                    .content(chunk)
+                    .ephemeral(ephemeral),
+            )
+            .await?;
+        if let Ok(mut msg) = handle.into_message().await {
+            let _ = msg
+                .edit(&ctx, EditMessage::new().suppress_embeds(true))
+                .await;
+        }
     }
     Ok(())
 }
@@ -0,0 +1,80 @@
+use crate::models::*;
+use crate::utils::*;
+fn parse_records(text: &str) -> Vec<Vec<String>> {
+    let mut records = Vec::new();
+    let mut record = Vec::new();
+    let mut field = String::new();
+    let mut inside_quotes = false;
+    let mut chars = text.trim_start_matches('\u{FEFF}').chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if inside_quotes && chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            }
+            '"' => inside_quotes = !inside_quotes,
+            ',' if !inside_quotes => record.push(std::mem::take(&mut field)),
+            '\r' if !inside_quotes => {}
+            '\n' if !inside_quotes => {
+                record.push(std::mem::take(&mut field));
+                records.push(std::mem::take(&mut record));
+            }
+            _ => field.push(c),
+        }
+    }
+    if !field.is_empty() || !record.is_empty() {
+        record.push(field);
+        records.push(record);
+    }
+    records
+}
+pub fn parse_csv_export(
+    text: &str,
+    export_name: &str,
+) -> Result<(Export, Vec<MessageInfo>), String> {
+    let mut records = parse_records(text).into_iter();
+    let header = records
+        .next()
+        .ok_or("Error parsing CSV: missing header row")?;
+    let column = |name: &str| {
+        header
+            .iter()
+            .position(|h| h.trim().eq_ignore_ascii_case(name))
+            .ok_or_else(|| format!("Error parsing CSV: missing {name} column"))
+    };
+    let author_id = column("AuthorID")?;
+    let author = column("Author")?;
+    let date = column("Date")?;
+    let content = column("Content")?;
+    let attachments = column("Attachments").ok();
+    let reactions = column("Reactions").ok();
+    let field = |record: &[String], index: usize| record.get(index).cloned().unwrap_or_default();
+    let messages = records
+        .filter(|record| record.iter().any(|field| !field.is_empty()))
+        .map(|record| MessageInfo {
+            id: String::new(),
+            content: field(&record, content),
+            author: plain_author(Some(&field(&record, author_id)), &field(&record, author)),
+            timestamp: normalize_timestamp(&field(&record, date)),
+            timestamp_edited: None,
+            attachments: attachments
+                .map(|index| field(&record, index))
+                .unwrap_or_default()
+                .split(',')
+                .map(str::trim)
+                .filter(|url| !url.is_empty())
+                .map(|url| AttachmentInfo {
+                    id: String::new(),
+                    url: url.to_string(),
+                    file_name: file_name_from_url(url),
+                })
+                .collect(),
+            mentions: Vec::new(),
+            inline_emojis: Vec::new(),
+            reactions: reactions
+                .map(|index| parse_reaction_counts(&field(&record, index)))
+                .unwrap_or_default(),
+        })
+        .collect();
+    Ok((plain_export(export_name), messages))
+}
@@ -0,0 +1,288 @@
+use crate::access::*;
+use crate::utils::*;
+use reqwest::{header, StatusCode};
+use std::{
+    env,
+    io::{Seek, Write},
+    path::Path,
+    sync::LazyLock,
+    time::Duration,
+};
+use tokio::sync::watch;
+static DRIVE_FILE_ID_PATTERN: LazyLock<regex::Regex> =
+    LazyLock::new(|| regex::Regex::new(r"/d/([\w-]+)").expect("valid regex"));
+static FORM_ACTION_PATTERN: LazyLock<regex::Regex> =
+    LazyLock::new(|| regex::Regex::new(r#"action="([^"]+)""#).expect("valid regex"));
+static HIDDEN_INPUT_PATTERN: LazyLock<regex::Regex> = LazyLock::new(|| {
+    regex::Regex::new(r#"<input type="hidden" name="([^"]+)" value="([^"]*)""#)
+        .expect("valid regex")
+});
+static FILE_NAME_PATTERN: LazyLock<regex::Regex> = LazyLock::new(|| {
+    regex::Regex::new(r#"filename\*?=(?:UTF-8'')?"?([^";]+)"?"#).expect("valid regex")
+});
+const MAX_REDIRECTS: usize = 10;
+const MAX_RESUMES: usize = 5;
+const RESUME_DELAY: Duration = Duration::from_secs(2);
+#[derive(Clone, Default)]
+pub struct DownloadProgress {
+    pub kind: String,
+    pub downloaded: u64,
+    pub total: Option<u64>,
+}
+pub type ProgressSender = watch::Sender<Option<DownloadProgress>>;
+pub struct Download {
+    pub file: tempfile::NamedTempFile,
+    pub file_name: String,
+}
+fn base64_url(input: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-_";
+    let mut output = String::new();
+    for chunk in input.chunks(3) {
+        let bits = chunk.iter().enumerate().fold(0u32, |bits, (i, byte)| {
+            bits | (*byte as u32) << (16 - 8 * i)
+        });
+        for i in 0..=chunk.len() {
+            output.push(ALPHABET[(bits >> (18 - 6 * i) & 63) as usize] as char);
+        }
+    }
+    output
+}
+fn query_value<'a>(url: &'a str, key: &str) -> Option<&'a str> {
+    url.split_once('?')?
+        .1
+        .split(['&', '#'])
+        .find_map(|pair| pair.strip_prefix(key)?.strip_prefix('='))
+}
+fn set_query_value(url: &str, key: &str, value: &str) -> String {
+    let Ok(mut parsed) = reqwest::Url::parse(url) else {
+        return url.to_string();
+    };
+    let pairs: Vec<(String, String)> = parsed
+        .query_pairs()
+        .into_owned()
+        .filter(|(name, _)| name != key)
+        .collect();
+    parsed
+        .query_pairs_mut()
+        .clear()
+        .extend_pairs(pairs)
+        .append_pair(key, value);
+    parsed.into()
+}
+pub fn direct_download_url(url: &str) -> String {
+    let host = url
+        .split("://")
+        .nth(1)
+        .and_then(|rest| rest.split(['/', '?']).next())
+        .unwrap_or("")
+        .to_ascii_lowercase();
+    if host == "drive.google.com" || host == "docs.google.com" {
+        let file_id = DRIVE_FILE_ID_PATTERN
+            .captures(url)
+            .map(|c| c[1].to_string())
+            .or_else(|| query_value(url, "id").map(str::to_string));
+        if let Some(file_id) = file_id {
+            return format!(
+                "https://drive.usercontent.google.com/download?id={file_id}&export=download"
+            );
+        }
+    }
+    if host.ends_with("dropbox.com") {
+        return set_query_value(url, "dl", "1");
+    }
+    if host == "1drv.ms" || host.ends_with("onedrive.live.com") {
+        return format!(
+            "https://api.onedrive.com/v1.0/shares/u!{}/root/content",
+            base64_url(url.as_bytes())
+        );
+    }
+    if host.ends_with("sharepoint.com") {
+        return set_query_value(url, "download", "1");
+    }
+    url.to_string()
+}
+fn google_confirm_url(page: &str) -> Option<String> {
+    let form = page.split("id=\"download-form\"").nth(1)?;
+    let action = FORM_ACTION_PATTERN.captures(form)?[1].replace("&amp;", "&");
+    let query: Vec<String> = HIDDEN_INPUT_PATTERN
+        .captures_iter(form.split("</form>").next().unwrap_or(form))
+        .map(|c| format!("{}={}", &c[1], &c[2]))
+        .collect();
+    Some(format!("{action}?{}", query.join("&")))
+}
+fn attachment_file_name(headers: &header::HeaderMap) -> Option<String> {
+    let disposition = headers.get(header::CONTENT_DISPOSITION)?.to_str().ok()?;
+    let name = FILE_NAME_PATTERN
+        .captures_iter(disposition)
+        .last()
+        .map(|c| c[1].replace("%20", " "))?;
+    Some(file_name_from_url(&name))
+}
+fn follow_redirect(attempt: reqwest::redirect::Attempt) -> reqwest::redirect::Action {
+    if attempt.previous().len() >= MAX_REDIRECTS {
+        attempt.error("too many redirects")
+    } else if !url_allowed(attempt.url().as_str()) {
+        let error = format!("redirect to {} is not on an allowed host", attempt.url());
+        attempt.error(error)
+    } else {
+        attempt.follow()
+    }
+}
+fn sends_auth(url: &str) -> bool {
+    env::var("DOWNLOAD_AUTH_HOSTS").is_ok_and(|hosts| url_host_in(url, &hosts))
+}
+fn with_auth(request: reqwest::RequestBuilder, url: &str) -> reqwest::RequestBuilder {
+    if !sends_auth(url) {
+        return request;
+    }
+    if let Ok(token) = env::var("DOWNLOAD_BEARER_TOKEN") {
+        return request.bearer_auth(token);
+    }
+    if let Ok(credentials) = env::var("DOWNLOAD_BASIC_AUTH") {
+        let (user, password) = credentials
+            .split_once(':')
+            .unwrap_or((credentials.as_str(), ""));
+        return request.basic_auth(user, Some(password));
+    }
+    request
+}
+fn max_download_size() -> Option<u64> {
+    env::var("DOWNLOAD_MAX_SIZE_MB")
+        .ok()
+        .and_then(|size| size.trim().parse::<u64>().ok())
+        .map(|size| size * 1024 * 1024)
+}
+fn check_size(size: u64, kind: &str) -> Result<(), String> {
+    match max_download_size() {
+        Some(limit) if size > limit => Err(format!(
+            "Error fetching {kind}: larger than the {} download limit",
+            format_size(limit)
+        )),
+        _ => Ok(()),
+    }
+}
+async fn open_response(
+    client: &reqwest::Client,
+    url: &str,
+    kind: &str,
+) -> Result<reqwest::Response, String> {
+    let mut url = direct_download_url(url);
+    for _ in 0..2 {
+        let response = with_auth(client.get(&url), &url)
+            .send()
+            .await
+            .map_err(|e| format!("Error fetching {kind}: {e}"))?;
+        if !response.status().is_success() {
+            return Err(format!("HTTP error: {}", response.status()));
+        }
+        let is_html = response
+            .headers()
+            .get(header::CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .is_some_and(|value| value.starts_with("text/html"));
+        let is_google = response
+            .url()
+            .host_str()
+            .is_some_and(|host| host.ends_with("google.com"));
+        if !(is_html && is_google) {
+            return Ok(response);
+        }
+        let page = response
+            .text()
+            .await
+            .map_err(|e| format!("Error reading {kind} body: {e}"))?;
+        url = google_confirm_url(&page)
+            .ok_or_else(|| format!("Error fetching {kind}: Google Drive file is not shared"))?;
+    }
+    Err(format!(
+        "Error fetching {kind}: Google Drive confirmation failed"
+    ))
+}
+pub fn http_client() -> reqwest::Result<reqwest::Client> {
+    reqwest::Client::builder()
+        .redirect(reqwest::redirect::Policy::custom(follow_redirect))
+        .user_agent(concat!("Dimport/", env!("CARGO_PKG_VERSION")))
+        .build()
+}
+pub async fn download_to_tempfile(
+    client: &reqwest::Client,
+    url: &str,
+    kind: &str,
+    progress: Option<&ProgressSender>,
+) -> Result<Download, String> {
+    let mut response = open_response(client, url, kind).await?;
+    let final_url = response.url().to_string();
+    let total = response.content_length();
+    if let Some(total) = total {
+        check_size(total, kind)?;
+    }
+    let file_name =
+        attachment_file_name(response.headers()).unwrap_or_else(|| file_name_from_url(&final_url));
+    let extension = Path::new(&file_name)
+        .extension()
+        .and_then(|s| s.to_str())
+        .map(|ext| format!(".{ext}"))
+        .unwrap_or_default();
+    let mut tmp = tempfile::Builder::new()
+        .suffix(&extension)
+        .tempfile()
+        .map_err(|e| format!("Error creating temp file: {e}"))?;
+    let mut downloaded = 0u64;
+    let mut resumes = 0;
+    loop {
+        match response.chunk().await {
+            Ok(Some(chunk)) => {
+                downloaded += chunk.len() as u64;
+                check_size(downloaded, kind)?;
+                tmp.write_all(&chunk)
+                    .map_err(|e| format!("Error writing temp {kind}: {e}"))?;
+                if let Some(progress) = progress {
+                    progress.send_replace(Some(DownloadProgress {
+                        kind: kind.to_string(),
+                        downloaded,
+                        total,
+                    }));
+                }
+            }
+            Ok(None) if total.is_none_or(|total| downloaded >= total) => break,
+            Err(e) if total.is_none() => {
+                return Err(format!("Error reading {kind} body: {e}"));
+            }
+            result => {
+                if resumes >= MAX_RESUMES {
+                    return Err(match result {
+                        Err(e) => format!("Error reading {kind} body: {e}"),
+                        _ => format!("Error reading {kind} body: connection closed early"),
+                    });
+                }
+                resumes += 1;
+                tokio::time::sleep(RESUME_DELAY).await;
+                let request = client
+                    .get(&final_url)
+                    .header(header::RANGE, format!("bytes={downloaded}-"));
+                let resumed = with_auth(request, &final_url)
+                    .send()
+                    .await
+                    .map_err(|e| format!("Error fetching {kind}: {e}"))?;
+                match resumed.status() {
+                    StatusCode::PARTIAL_CONTENT => {}
+                    StatusCode::OK => {
+                        downloaded = 0;
+                        tmp.as_file_mut()
+                            .set_len(0)
+                            .and_then(|_| tmp.rewind())
+                            .map_err(|e| format!("Error writing temp {kind}: {e}"))?;
+                    }
+                    status => return Err(format!("HTTP error: {status}")),
+                }
+                response = resumed;
+            }
+        }
+    }
+    tmp.flush()
+        .map_err(|e| format!("Error flushing temp {kind}: {e}"))?;
+    Ok(Download {
+        file: tmp,
+        file_name,
+    })
+}
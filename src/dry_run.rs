@@ -0,0 +1,144 @@
+use crate::cdn::*;
+use crate::models::*;
+use crate::utils::*;
+use std::collections::HashSet;
+fn unknown_emoji(emoji: &EmojiInfo, guild_emojis: &HashSet<String>) -> Option<String> {
+    let id = emoji.id.as_deref().filter(|id| !id.is_empty())?;
+    (!guild_emojis.contains(id)).then(|| format!(":{}:", emoji.name))
+}
+pub fn record_message(
+    report: &mut DryRunReport,
+    message: &MessageInfo,
+    file_index: &Option<FileIndex>,
+    ambiguous_media: &mut Vec<String>,
+    guild_emojis: &HashSet<String>,
+    options: &ImportOptions,
+) {
+    report.messages += 1;
+    let sources = collect_sources(message, file_index, ambiguous_media, |attachment| {
+        options.outside || is_image_file(&attachment.file_name)
+    });
+    let mut uploads = 0;
+    let mut has_expired = false;
+    for source in &sources {
+        match source {
+            MediaSource::Local(file, filename) => {
+                report.local_media += 1;
+                uploads += 1;
+                if let Some(size) = media_size(file).filter(|&size| size > MAX_UPLOAD_SIZE) {
+                    report
+                        .oversized_files
+                        .push(format!("{filename} ({})", format_size(size)));
+                }
+            }
+            MediaSource::Remote(url) if is_expired_cdn_url(url) => {
+                report.expired_media += 1;
+                uploads += 1;
+                has_expired = true;
+            }
+            MediaSource::Remote(url) if is_signed_cdn_url(url) => {
+                report.remote_media += 1;
+                uploads += 1;
+            }
+            MediaSource::Remote(url) if is_url(url) => report.remote_media += 1,
+            _ => report.missing_media += 1,
+        }
+    }
+    let content = replace_mentions(&message.content, &message.mentions, options.no_mentions);
+    let content = replace_emojis(&content, &message.inline_emojis);
+    let length_limit = if options.outside {
+        MAX_MESSAGE_LENGTH
+    } else {
+        MAX_DESCRIPTION_LENGTH
+    };
+    if content.chars().count() > length_limit {
+        report.long_messages.push(message.id.clone());
+    }
+    report.sends += if options.outside {
+        let has_content = !content.is_empty() || uploads < sources.len();
+        usize::from(!options.no_embed)
+            + if has_content || uploads > 0 {
+                uploads.div_ceil(MAX_ATTACHMENTS).max(1)
+            } else {
+                0
+            }
+    } else {
+        sources.len().div_ceil(MAX_EMBEDS).max(1)
+    };
+    if options.reaction_users && !message.reactions.is_empty() {
+        report.sends += 1;
+    }
+    if !options.button && !options.no_reactions {
+        report.reactions += message.reactions.len();
+    }
+    if message.author.id.is_some() {
+        report.lookups += usize::from(options.current_avatar) + usize::from(options.accent_color);
+    }
+    report.lookups += usize::from(has_expired);
+    let reaction_emojis = message
+        .reactions
+        .iter()
+        .filter(|_| !options.no_reactions)
+        .map(|reaction| &reaction.emoji);
+    for emoji in message.inline_emojis.iter().chain(reaction_emojis) {
+        if let Some(name) = unknown_emoji(emoji, guild_emojis) {
+            report.unknown_emojis.insert(name);
+        }
+    }
+}
+fn format_items(items: &[String]) -> String {
+    let mut text = items
+        .iter()
+        .take(MAX_REPORT_ITEMS)
+        .cloned()
+        .collect::<Vec<_>>()
+        .join(", ");
+    if items.len() > MAX_REPORT_ITEMS {
+        text.push_str(&format!(" and {} more", items.len() - MAX_REPORT_ITEMS));
+    }
+    text
+}
+pub fn format_dry_run_report(report: &DryRunReport, ambiguous_media: usize) -> String {
+    let duration = ESTIMATED_SEND_TIME * report.sends as u32
+        + ESTIMATED_REACTION_TIME * report.reactions as u32;
+    let mut lines = vec![
+        format!("Dry run: {} messages would be imported", report.messages),
+        format!(
+            "API calls: ~{} ({} messages, {} reactions, {} other)",
+            report.sends + report.reactions + report.lookups,
+            report.sends,
+            report.reactions,
+            report.lookups
+        ),
+        format!("Estimated duration: ~{}", format_duration(duration)),
+        format!(
+            "Media: {} local, {} remote, {} expired CDN links to refresh, {} missing, {} ambiguous",
+            report.local_media,
+            report.remote_media,
+            report.expired_media,
+            report.missing_media,
+            ambiguous_media
+        ),
+    ];
+    if !report.oversized_files.is_empty() {
+        lines.push(format!(
+            "Files over the {} upload limit: {}",
+            format_size(MAX_UPLOAD_SIZE),
+            format_items(&report.oversized_files)
+        ));
+    }
+    if !report.unknown_emojis.is_empty() {
+        let emojis: Vec<String> = report.unknown_emojis.iter().cloned().collect();
+        lines.push(format!(
+            "Custom emojis missing from this server: {}",
+            format_items(&emojis)
+        ));
+    }
+    if !report.long_messages.is_empty() {
+        lines.push(format!(
+            "Messages over the length limit: {}",
+            format_items(&report.long_messages)
+        ));
+    }
+    lines.join("\n")
+}
@@ -0,0 +1,243 @@
+use crate::models::*;
+use crate::utils::*;
+use poise::serenity_prelude::{self as serenity};
+use scraper::{ElementRef, Html, Node, Selector};
+use std::sync::LazyLock;
+static AUTHOR_SELECTOR: LazyLock<Selector> = LazyLock::new(|| selector(".chatlog__author"));
+static AVATAR_SELECTOR: LazyLock<Selector> = LazyLock::new(|| selector(".chatlog__avatar"));
+static BOT_TAG_SELECTOR: LazyLock<Selector> = LazyLock::new(|| selector(".chatlog__bot-tag"));
+static CONTENT_SELECTOR: LazyLock<Selector> =
+    LazyLock::new(|| selector(".chatlog__content .chatlog__markdown-preserve"));
+static ATTACHMENT_SELECTOR: LazyLock<Selector> = LazyLock::new(|| selector(".chatlog__attachment"));
+static LINK_SELECTOR: LazyLock<Selector> = LazyLock::new(|| selector("a[href]"));
+static REACTION_SELECTOR: LazyLock<Selector> = LazyLock::new(|| selector(".chatlog__reaction"));
+static IMAGE_SELECTOR: LazyLock<Selector> = LazyLock::new(|| selector("img"));
+static REACTION_COUNT_SELECTOR: LazyLock<Selector> =
+    LazyLock::new(|| selector(".chatlog__reaction-count"));
+static PREAMBLE_SELECTOR: LazyLock<Selector> = LazyLock::new(|| selector(".preamble__entry"));
+static CHATLOG_SELECTOR: LazyLock<Selector> = LazyLock::new(|| selector(".chatlog"));
+static MESSAGE_CONTAINER_SELECTOR: LazyLock<Selector> =
+    LazyLock::new(|| selector(".chatlog__message-container"));
+fn selector(css: &str) -> Selector {
+    Selector::parse(css).expect("valid selector")
+}
+fn has_class(element: ElementRef, class: &str) -> bool {
+    element.value().classes().any(|c| c == class)
+}
+fn parse_rgb_color(style: &str) -> Option<String> {
+    let rgb = style.split("rgb(").nth(1)?.split(')').next()?;
+    let channels: Vec<u8> = rgb
+        .split(',')
+        .filter_map(|channel| channel.trim().parse().ok())
+        .collect();
+    match channels[..] {
+        [r, g, b] => Some(format!("#{r:02X}{g:02X}{b:02X}")),
+        _ => None,
+    }
+}
+fn parse_emoji(image: ElementRef) -> EmojiInfo {
+    let alt = image.value().attr("alt").unwrap_or("");
+    let src = image.value().attr("src").unwrap_or("");
+    let name = alt.trim_matches(':').to_string();
+    let custom_id = src
+        .contains("/emojis/")
+        .then(|| src.rsplit('/').next())
+        .flatten()
+        .and_then(|file| file.split('.').next())
+        .filter(|id| id.parse::<u64>().is_ok());
+    EmojiInfo {
+        id: custom_id.map(|id| id.to_string()),
+        code: name.clone(),
+        is_animated: src.contains(".gif"),
+        image_url: src.to_string(),
+        name,
+    }
+}
+fn render_children(element: ElementRef, content: &mut String, emojis: &mut Vec<EmojiInfo>) {
+    for child in element.children() {
+        match child.value() {
+            Node::Text(text) => content.push_str(text),
+            Node::Element(_) => {
+                if let Some(child) = ElementRef::wrap(child) {
+                    render_markdown(child, content, emojis);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+fn wrap_markdown(
+    element: ElementRef,
+    marker: &str,
+    content: &mut String,
+    emojis: &mut Vec<EmojiInfo>,
+) {
+    content.push_str(marker);
+    render_children(element, content, emojis);
+    content.push_str(marker);
+}
+fn render_markdown(element: ElementRef, content: &mut String, emojis: &mut Vec<EmojiInfo>) {
+    if has_class(element, "chatlog__edited-timestamp") {
+        return;
+    }
+    if has_class(element, "chatlog__emoji") {
+        let emoji = parse_emoji(element);
+        if emoji.id.is_some() {
+            content.push_str(&format!(":{}:", emoji.code));
+            emojis.push(emoji);
+        } else {
+            content.push_str(&emoji.name);
+        }
+        return;
+    }
+    if has_class(element, "chatlog__markdown-spoiler-text") {
+        return wrap_markdown(element, "||", content, emojis);
+    }
+    if has_class(element, "chatlog__markdown-pre--multiline") {
+        let code: String = element.text().collect();
+        content.push_str(&format!("```\n{}\n```", code.trim_end_matches('\n')));
+        return;
+    }
+    if has_class(element, "chatlog__markdown-pre--inline") {
+        let code: String = element.text().collect();
+        content.push_str(&format!("`{code}`"));
+        return;
+    }
+    if has_class(element, "chatlog__markdown-quote") {
+        let mut quote = String::new();
+        render_children(element, &mut quote, emojis);
+        let quoted: Vec<String> = quote.lines().map(|line| format!("> {line}")).collect();
+        content.push_str(&quoted.join("\n"));
+        content.push('\n');
+        return;
+    }
+    match element.value().name() {
+        "strong" | "b" => wrap_markdown(element, "**", content, emojis),
+        "em" | "i" => wrap_markdown(element, "*", content, emojis),
+        "u" => wrap_markdown(element, "__", content, emojis),
+        "s" | "del" => wrap_markdown(element, "~~", content, emojis),
+        "br" => content.push('\n'),
+        "a" => {
+            let text: String = element.text().collect();
+            match element.value().attr("href") {
+                Some(href) if href != text && !text.is_empty() => {
+                    content.push_str(&format!("[{text}]({href})"));
+                }
+                Some(href) => content.push_str(href),
+                None => content.push_str(&text),
+            }
+        }
+        _ => render_children(element, content, emojis),
+    }
+}
+fn parse_author(container: ElementRef) -> Option<Author> {
+    let author = container.select(&AUTHOR_SELECTOR).next()?;
+    let id = author
+        .value()
+        .attr("data-user-id")
+        .and_then(|id| id.parse::<u64>().ok())
+        .filter(|id| *id > 0)?;
+    let avatar_url = container
+        .select(&AVATAR_SELECTOR)
+        .next()
+        .and_then(|avatar| avatar.value().attr("src"))
+        .unwrap_or("")
+        .to_string();
+    Some(Author {
+        id: Some(serenity::UserId::new(id)),
+        name: author.text().collect::<String>().trim().to_string(),
+        avatar_url,
+        color: author.value().attr("style").and_then(parse_rgb_color),
+        is_bot: container.select(&BOT_TAG_SELECTOR).next().is_some(),
+    })
+}
+fn parse_message(container: ElementRef, author: &Author) -> Option<MessageInfo> {
+    let id = container.value().attr("data-message-id")?.to_string();
+    let timestamp = snowflake_timestamp(&id)?;
+    let mut content = String::new();
+    let mut inline_emojis = Vec::new();
+    if let Some(body) = container.select(&CONTENT_SELECTOR).next() {
+        render_markdown(body, &mut content, &mut inline_emojis);
+    }
+    let attachments = container
+        .select(&ATTACHMENT_SELECTOR)
+        .filter_map(|attachment| {
+            let url = attachment
+                .select(&LINK_SELECTOR)
+                .next()
+                .and_then(|link| link.value().attr("href"))?;
+            Some(AttachmentInfo {
+                id: String::new(),
+                url: url.to_string(),
+                file_name: file_name_from_url(url),
+            })
+        })
+        .collect();
+    let reactions = container
+        .select(&REACTION_SELECTOR)
+        .filter_map(|reaction| {
+            let emoji = parse_emoji(reaction.select(&IMAGE_SELECTOR).next()?);
+            let count = reaction
+                .select(&REACTION_COUNT_SELECTOR)
+                .next()
+                .and_then(|count| count.text().collect::<String>().trim().parse::<u64>().ok())
+                .unwrap_or(1);
+            Some(ReactionInfo {
+                emoji,
+                count: count.into(),
+                users: Vec::new(),
+            })
+        })
+        .collect();
+    Some(MessageInfo {
+        id,
+        content: content.trim().to_string(),
+        author: author.clone(),
+        timestamp,
+        timestamp_edited: None,
+        attachments,
+        mentions: Vec::new(),
+        inline_emojis,
+        reactions,
+    })
+}
+fn parse_header(document: &Html) -> Export {
+    let entries: Vec<String> = document
+        .select(&PREAMBLE_SELECTOR)
+        .map(|entry| entry.text().collect::<String>().trim().to_string())
+        .collect();
+    let guild_name = entries.first().cloned().unwrap_or_default();
+    let channel_path = entries.get(1).cloned().unwrap_or_default();
+    let (category, channel_name) = match channel_path.rsplit_once(" / ") {
+        Some((category, name)) => (Some(category.to_string()), name.to_string()),
+        None => (None, channel_path),
+    };
+    Export {
+        guild: GuildInfo { name: guild_name },
+        channel: ChannelInfo {
+            name: channel_name,
+            category,
+        },
+    }
+}
+pub fn parse_html_export(html: &str) -> Result<(Export, Vec<MessageInfo>), String> {
+    let document = Html::parse_document(html);
+    if document.select(&CHATLOG_SELECTOR).next().is_none() {
+        return Err("Error parsing HTML: not a DiscordChatExporter HTML export".to_string());
+    }
+    let export = parse_header(&document);
+    let mut messages = Vec::new();
+    let mut author: Option<Author> = None;
+    for container in document.select(&MESSAGE_CONTAINER_SELECTOR) {
+        if let Some(message_author) = parse_author(container) {
+            author = Some(message_author);
+        }
+        let Some(author) = &author else {
+            continue;
+        };
+        if let Some(message) = parse_message(container, author) {
+            messages.push(message);
+        }
+    }
+    Ok((export, messages))
+}
@@ -0,0 +1,341 @@
+use crate::models::*;
+use crate::utils::*;
+use poise::serenity_prelude::{self as serenity};
+use std::{cmp::Reverse, collections::HashMap, env, fs, sync::Mutex};
+static SAVED_JOBS_VERSION: Mutex<u64> = Mutex::new(0);
+impl JobState {
+    pub fn is_active(self) -> bool {
+        matches!(self, Self::Queued | Self::Running | Self::Paused)
+    }
+    fn is_finished(self) -> bool {
+        matches!(self, Self::Cancelled | Self::Done | Self::Failed)
+    }
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Queued => "queued",
+            Self::Running => "running",
+            Self::Paused => "paused",
+            Self::Cancelled => "cancelled",
+            Self::Done => "done",
+            Self::Failed => "failed",
+            Self::Interrupted => "interrupted",
+        }
+    }
+}
+impl Job {
+    fn visible_to(&self, guild_id: Option<serenity::GuildId>, user_id: serenity::UserId) -> bool {
+        match guild_id {
+            Some(_) => self.guild_id == guild_id,
+            None => self.guild_id.is_none() && self.owner == user_id,
+        }
+    }
+    fn progress_label(&self) -> String {
+        match self.total {
+            Some(total) => format!("{}/{total}", self.done),
+            None => format!("{}/?", self.done),
+        }
+    }
+}
+pub fn create_job(
+    data: &Data,
+    owner: serenity::UserId,
+    guild_id: Option<serenity::GuildId>,
+    channel_id: serenity::ChannelId,
+    arguments: &[String],
+    options: &ImportOptions,
+) -> u64 {
+    let mut registry = data.jobs.lock().unwrap();
+    registry.next_id += 1;
+    let id = registry.next_id;
+    registry.jobs.insert(
+        id,
+        Job {
+            id,
+            state: JobState::Queued,
+            owner,
+            guild_id,
+            channel_id,
+            arguments: arguments.to_vec(),
+            priority: options.priority,
+            start_at: options.start_at,
+            total: None,
+            done: 0,
+            failures: 0,
+            created: serenity::Timestamp::now(),
+            error: None,
+        },
+    );
+    let finished: Vec<u64> = registry
+        .jobs
+        .values()
+        .filter(|job| job.state.is_finished())
+        .map(|job| job.id)
+        .collect();
+    for id in finished
+        .iter()
+        .take(finished.len().saturating_sub(MAX_FINISHED_JOBS))
+    {
+        registry.jobs.remove(id);
+    }
+    drop(registry);
+    save_jobs(data);
+    id
+}
+pub fn job_state(data: &Data, id: u64) -> Option<JobState> {
+    data.jobs.lock().unwrap().jobs.get(&id).map(|job| job.state)
+}
+pub fn request_job_state(data: &Data, id: u64, state: JobState) -> bool {
+    let mut registry = data.jobs.lock().unwrap();
+    let Some(job) = registry.jobs.get_mut(&id) else {
+        return false;
+    };
+    let allowed = match state {
+        JobState::Paused => job.state == JobState::Running,
+        JobState::Running => job.state == JobState::Paused,
+        JobState::Cancelled => job.state.is_active() || job.state == JobState::Interrupted,
+        _ => false,
+    };
+    if allowed {
+        job.state = state;
+        drop(registry);
+        save_jobs(data);
+    }
+    allowed
+}
+fn import_limit(name: &str, default: usize) -> usize {
+    env::var(name)
+        .ok()
+        .and_then(|limit| limit.trim().parse::<usize>().ok())
+        .filter(|&limit| limit > 0)
+        .unwrap_or(default)
+}
+fn queued_jobs(registry: &JobRegistry) -> Vec<&Job> {
+    let mut queued: Vec<&Job> = registry
+        .jobs
+        .values()
+        .filter(|job| {
+            job.state == JobState::Queued
+                && job
+                    .start_at
+                    .is_none_or(|start_at| start_at <= serenity::Timestamp::now())
+        })
+        .collect();
+    queued.sort_by_key(|job| (Reverse(job.priority), job.id));
+    queued
+}
+pub fn try_start_job(data: &Data, id: u64) -> bool {
+    let global_limit = import_limit("MAX_IMPORTS", DEFAULT_MAX_IMPORTS);
+    let guild_limit = import_limit("MAX_GUILD_IMPORTS", DEFAULT_MAX_GUILD_IMPORTS);
+    let mut registry = data.jobs.lock().unwrap();
+    let mut running: HashMap<Option<serenity::GuildId>, usize> = HashMap::new();
+    for job in registry
+        .jobs
+        .values()
+        .filter(|job| matches!(job.state, JobState::Running | JobState::Paused))
+    {
+        *running.entry(job.guild_id).or_default() += 1;
+    }
+    let mut total: usize = running.values().sum();
+    let mut admitted = false;
+    for job in queued_jobs(&registry) {
+        if total >= global_limit {
+            break;
+        }
+        let count = running.entry(job.guild_id).or_default();
+        if *count >= guild_limit {
+            continue;
+        }
+        if job.id == id {
+            admitted = true;
+            break;
+        }
+        *count += 1;
+        total += 1;
+    }
+    if let Some(job) = registry.jobs.get_mut(&id).filter(|_| admitted) {
+        job.state = JobState::Running;
+        drop(registry);
+        save_jobs(data);
+    }
+    admitted
+}
+pub fn queue_position(data: &Data, id: u64) -> usize {
+    let registry = data.jobs.lock().unwrap();
+    queued_jobs(&registry)
+        .iter()
+        .position(|job| job.id == id)
+        .map_or(0, |position| position + 1)
+}
+pub fn update_job_progress(data: &Data, id: u64, progress: &ImportProgress) {
+    let mut registry = data.jobs.lock().unwrap();
+    if let Some(job) = registry.jobs.get_mut(&id) {
+        job.total = progress.total;
+        job.done = progress.done;
+        job.failures = progress.failures;
+    }
+}
+pub fn finish_job(data: &Data, id: u64, state: JobState, error: Option<String>) {
+    let mut registry = data.jobs.lock().unwrap();
+    if let Some(job) = registry.jobs.get_mut(&id) {
+        job.state = state;
+        job.error = error;
+        drop(registry);
+        save_jobs(data);
+    }
+}
+pub fn requeue_job(
+    data: &Data,
+    id: u64,
+    channel_id: serenity::ChannelId,
+) -> Result<(Vec<String>, usize), String> {
+    let mut registry = data.jobs.lock().unwrap();
+    let Some(job) = registry.jobs.get_mut(&id) else {
+        return Err(format!("No job #{id}."));
+    };
+    if job.state != JobState::Interrupted {
+        return Err(format!("Job #{id} was not interrupted."));
+    }
+    if job.channel_id != channel_id {
+        return Err(format!(
+            "Job #{id} imports into <#{}>, resume it there.",
+            job.channel_id
+        ));
+    }
+    job.state = JobState::Queued;
+    job.error = None;
+    let resumed = (job.arguments.clone(), job.done);
+    drop(registry);
+    save_jobs(data);
+    Ok(resumed)
+}
+pub fn save_jobs(data: &Data) {
+    let (version, content) = {
+        let mut registry = data.jobs.lock().unwrap();
+        registry.version += 1;
+        let Ok(content) = serde_json::to_string(&*registry) else {
+            return;
+        };
+        (registry.version, content)
+    };
+    let mut saved_version = SAVED_JOBS_VERSION.lock().unwrap();
+    if version <= *saved_version {
+        return;
+    }
+    let temp_path = format!("{JOB_STORE_PATH}.tmp");
+    if fs::write(&temp_path, content).is_ok() && fs::rename(temp_path, JOB_STORE_PATH).is_ok() {
+        *saved_version = version;
+    }
+}
+pub fn load_jobs(path: &str) -> JobRegistry {
+    let mut registry = fs::read_to_string(path)
+        .ok()
+        .and_then(|content| serde_json::from_str::<JobRegistry>(&content).ok())
+        .unwrap_or_default();
+    for job in registry.jobs.values_mut() {
+        if job.state.is_active() {
+            job.state = JobState::Interrupted;
+        }
+    }
+    registry
+}
+pub async fn announce_interrupted_jobs(http: &serenity::Http, registry: &JobRegistry) {
+    for job in registry
+        .jobs
+        .values()
+        .filter(|job| job.state == JobState::Interrupted)
+    {
+        let content = format!(
+            "Job #{} was interrupted at {} messages by a restart. Use `/resume {}` to continue it or `/cancel {}` to drop it.",
+            job.id,
+            job.progress_label(),
+            job.id,
+            job.id
+        );
+        let _ = job.channel_id.say(http, content).await;
+    }
+}
+pub fn channel_jobs(data: &Data, channel_id: serenity::ChannelId) -> Vec<u64> {
+    data.jobs
+        .lock()
+        .unwrap()
+        .jobs
+        .values()
+        .filter(|job| job.channel_id == channel_id && job.state.is_active())
+        .map(|job| job.id)
+        .collect()
+}
+fn format_job_line(job: &Job) -> String {
+    format!(
+        "`#{}` {} {} in <#{}> by <@{}>",
+        job.id,
+        job.state.label(),
+        job.progress_label(),
+        job.channel_id,
+        job.owner
+    )
+}
+pub fn format_jobs(
+    data: &Data,
+    guild_id: Option<serenity::GuildId>,
+    user_id: serenity::UserId,
+) -> String {
+    let registry = data.jobs.lock().unwrap();
+    let jobs: Vec<&Job> = registry
+        .jobs
+        .values()
+        .rev()
+        .filter(|job| job.visible_to(guild_id, user_id))
+        .take(MAX_LISTED_JOBS)
+        .collect();
+    if jobs.is_empty() {
+        return "No import jobs.".to_string();
+    }
+    jobs.into_iter()
+        .map(format_job_line)
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+pub fn job_visible(
+    data: &Data,
+    id: u64,
+    guild_id: Option<serenity::GuildId>,
+    user_id: serenity::UserId,
+) -> bool {
+    let registry = data.jobs.lock().unwrap();
+    registry
+        .jobs
+        .get(&id)
+        .is_some_and(|job| job.visible_to(guild_id, user_id))
+}
+pub fn format_job_status(
+    data: &Data,
+    id: u64,
+    guild_id: Option<serenity::GuildId>,
+    user_id: serenity::UserId,
+) -> Option<String> {
+    let registry = data.jobs.lock().unwrap();
+    let job = registry
+        .jobs
+        .get(&id)
+        .filter(|job| job.visible_to(guild_id, user_id))?;
+    let mut lines = vec![
+        format!("**Job #{}**: {}", job.id, job.state.label()),
+        format!("Owner: <@{}>", job.owner),
+        format!("Channel: <#{}>", job.channel_id),
+        format!(
+            "Source: `{}`",
+            extract_export_name(job.arguments.first().map_or("", String::as_str))
+        ),
+        format!("Priority: {}", job.priority),
+        format!("Progress: {}", job.progress_label()),
+        format!("Failures: {}", job.failures),
+        format!("Created: <t:{}:f>", job.created.unix_timestamp()),
+    ];
+    if let Some(start_at) = job.start_at {
+        lines.push(format!("Starts at: <t:{}:f>", start_at.unix_timestamp()));
+    }
+    if let Some(error) = &job.error {
+        lines.push(format!("Error: {error}"));
+    }
+    Some(lines.join("\n"))
+}
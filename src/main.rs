@@ -1,10 +1,32 @@
-use crate::cli::{cancel, help, import};
-use crate::models::{Data, Error};
-use crate::utils::{ask_token, save_token};
+use crate::cli::{cancel, event_handler, help, import, jobs, resume, status};
+use crate::download::http_client;
+use crate::jobs::{announce_interrupted_jobs, load_jobs};
+use crate::models::{Data, Error, JOB_STORE_PATH, REACTION_STORE_PATH};
+use crate::utils::{ask_token, load_reaction_store, save_token};
 use poise::serenity_prelude as serenity;
-use std::{env, process};
+use std::{
+    env, process,
+    sync::{Arc, Mutex},
+};
+mod access;
+mod archive;
+mod cdn;
 mod cli;
+mod csv;
+mod download;
+mod dry_run;
+mod html;
+mod jobs;
+mod matrix;
 mod models;
+mod progress;
+mod report;
+mod scheduler;
+mod slack;
+mod source;
+mod stream;
+mod telegram;
+mod text;
 mod utils;
 #[tokio::main]
 async fn main() -> Result<(), Error> {
@@ -23,18 +45,28 @@ async fn main() -> Result<(), Error> {
         | serenity::GatewayIntents::MESSAGE_CONTENT;
     let framework = poise::Framework::builder()
         .options(poise::FrameworkOptions {
-            commands: vec![import(), cancel(), help()],
+            commands: vec![import(), resume(), cancel(), jobs(), status(), help()],
             prefix_options: poise::PrefixFrameworkOptions {
                 prefix: Some("/".into()),
                 ..Default::default()
             },
+            event_handler: |ctx, event, framework, data| {
+                Box::pin(event_handler(ctx, event, framework, data))
+            },
             ..Default::default()
         })
         .setup(|ctx, ready, framework| {
             Box::pin(async move {
                 println!("{} connected", ready.user.name);
                 poise::builtins::register_globally(ctx, &framework.options().commands).await?;
-                Ok(Data::default())
+                let jobs = load_jobs(JOB_STORE_PATH);
+                announce_interrupted_jobs(&ctx.http, &jobs).await;
+                Ok(Data {
+                    jobs: Arc::new(Mutex::new(jobs)),
+                    reaction_store: Arc::new(Mutex::new(load_reaction_store(REACTION_STORE_PATH))),
+                    http_client: http_client()?,
+                    ..Default::default()
+                })
             })
         })
         .build();
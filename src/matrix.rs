@@ -0,0 +1,187 @@
+use crate::models::*;
+use crate::utils::*;
+use serde::Deserialize;
+use serde_json::Value;
+use std::{collections::HashMap, fs, io::BufReader, path::Path};
+const MEDIA_MESSAGE_TYPES: [&str; 4] = ["m.image", "m.file", "m.video", "m.audio"];
+#[derive(Deserialize)]
+struct MatrixExport {
+    #[serde(default)]
+    room_name: String,
+    messages: Vec<MatrixEvent>,
+}
+#[derive(Deserialize, Default)]
+#[serde(default)]
+struct MatrixEvent {
+    #[serde(rename = "type")]
+    kind: String,
+    sender: String,
+    state_key: Option<String>,
+    origin_server_ts: i64,
+    event_id: String,
+    content: Value,
+}
+#[derive(Default)]
+struct Member {
+    name: Option<String>,
+    avatar_url: Option<String>,
+}
+fn mxc_to_http(url: &str) -> Option<String> {
+    let (server, media_id) = url.strip_prefix("mxc://")?.split_once('/')?;
+    Some(format!(
+        "https://{server}/_matrix/media/v3/download/{server}/{media_id}"
+    ))
+}
+fn text<'a>(value: &'a Value, key: &str) -> Option<&'a str> {
+    value.get(key).and_then(Value::as_str)
+}
+fn strip_reply_fallback(body: &str) -> String {
+    let mut lines = body.lines().peekable();
+    if !lines.peek().is_some_and(|line| line.starts_with("> <")) {
+        return body.to_string();
+    }
+    while lines.peek().is_some_and(|line| line.starts_with('>')) {
+        lines.next();
+    }
+    lines
+        .collect::<Vec<_>>()
+        .join("\n")
+        .trim_start()
+        .to_string()
+}
+fn sender_name(sender: &str, members: &HashMap<String, Member>) -> String {
+    members
+        .get(sender)
+        .and_then(|member| member.name.clone())
+        .unwrap_or_else(|| {
+            sender
+                .trim_start_matches('@')
+                .split(':')
+                .next()
+                .unwrap_or(sender)
+                .to_string()
+        })
+}
+fn render_content(content: &Value, author: &str) -> (String, Vec<AttachmentInfo>) {
+    let body = strip_reply_fallback(text(content, "body").unwrap_or(""));
+    let msgtype = text(content, "msgtype").unwrap_or("");
+    if msgtype == "m.emote" {
+        return (format!("*{author} {body}*"), Vec::new());
+    }
+    let url =
+        text(content, "url").or_else(|| content.get("file").and_then(|file| text(file, "url")));
+    let is_media = MEDIA_MESSAGE_TYPES.contains(&msgtype) || url.is_some();
+    let Some(url) = url.filter(|_| is_media) else {
+        return (body, Vec::new());
+    };
+    let file_name = text(content, "filename").unwrap_or(&body).to_string();
+    let caption = if text(content, "filename").is_some_and(|name| name != body) {
+        body.clone()
+    } else {
+        String::new()
+    };
+    let attachment = AttachmentInfo {
+        id: String::new(),
+        url: mxc_to_http(url).unwrap_or_else(|| url.to_string()),
+        file_name,
+    };
+    (caption, vec![attachment])
+}
+fn parent_event(relates_to: &Value) -> Option<&str> {
+    relates_to
+        .get("m.in_reply_to")
+        .and_then(|reply| text(reply, "event_id"))
+        .or_else(|| {
+            (text(relates_to, "rel_type") == Some("m.thread"))
+                .then(|| text(relates_to, "event_id"))
+                .flatten()
+        })
+}
+pub fn parse_matrix_export(path: &Path) -> Result<(Export, Vec<MessageInfo>), String> {
+    let file = fs::File::open(path).map_err(|e| format!("Error reading JSON file: {e}"))?;
+    let export: MatrixExport = serde_json::from_reader(BufReader::new(file))
+        .map_err(|e| format!("Error parsing Matrix export: {e}"))?;
+    let mut members: HashMap<String, Member> = HashMap::new();
+    let mut messages: Vec<MessageInfo> = Vec::new();
+    let mut quotes: Vec<String> = Vec::new();
+    let mut positions: HashMap<String, usize> = HashMap::new();
+    for event in export.messages {
+        let relates_to = event
+            .content
+            .get("m.relates_to")
+            .cloned()
+            .unwrap_or_default();
+        let rel_type = text(&relates_to, "rel_type");
+        let target = text(&relates_to, "event_id").and_then(|id| positions.get(id).copied());
+        match event.kind.as_str() {
+            "m.room.member" => {
+                let member = members
+                    .entry(event.state_key.unwrap_or(event.sender))
+                    .or_default();
+                if let Some(name) = text(&event.content, "displayname") {
+                    member.name = Some(name.to_string());
+                }
+                if let Some(avatar_url) = text(&event.content, "avatar_url").and_then(mxc_to_http) {
+                    member.avatar_url = Some(avatar_url);
+                }
+            }
+            "m.reaction" if rel_type == Some("m.annotation") => {
+                let (Some(index), Some(key)) = (target, text(&relates_to, "key")) else {
+                    continue;
+                };
+                let emoji = EmojiInfo {
+                    name: key.to_string(),
+                    code: key.to_string(),
+                    ..Default::default()
+                };
+                let user = sender_name(&event.sender, &members);
+                add_reaction(&mut messages[index].reactions, emoji, Some(&user));
+            }
+            "m.room.message" if rel_type == Some("m.replace") => {
+                let Some(index) = target else {
+                    continue;
+                };
+                let new_content = event.content.get("m.new_content").unwrap_or(&event.content);
+                let (content, _) = render_content(new_content, &messages[index].author.name);
+                messages[index].content = format!("{}{content}", quotes[index]);
+                messages[index].timestamp_edited =
+                    Some(unix_timestamp(event.origin_server_ts / 1000));
+            }
+            "m.room.message" | "m.sticker" => {
+                if event.content.get("body").is_none() {
+                    continue;
+                }
+                let name = sender_name(&event.sender, &members);
+                let (content, attachments) = render_content(&event.content, &name);
+                let quote = parent_event(&relates_to)
+                    .and_then(|id| positions.get(id))
+                    .map(|&index| {
+                        reply_quote(&messages[index].author.name, &messages[index].content)
+                    })
+                    .unwrap_or_default();
+                let avatar_url = members
+                    .get(&event.sender)
+                    .and_then(|member| member.avatar_url.clone())
+                    .unwrap_or_default();
+                positions.insert(event.event_id.clone(), messages.len());
+                messages.push(MessageInfo {
+                    id: event.event_id,
+                    content: format!("{quote}{content}"),
+                    author: Author {
+                        avatar_url,
+                        ..plain_author(None, &name)
+                    },
+                    timestamp: unix_timestamp(event.origin_server_ts / 1000),
+                    timestamp_edited: None,
+                    attachments,
+                    mentions: Vec::new(),
+                    inline_emojis: Vec::new(),
+                    reactions: Vec::new(),
+                });
+                quotes.push(quote);
+            }
+            _ => {}
+        }
+    }
+    Ok((plain_export(&export.room_name), messages))
+}
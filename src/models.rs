@@ -1,28 +1,110 @@
+use crate::scheduler::Scheduler;
 use poise::serenity_prelude::{self as serenity};
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 use std::{
-    collections::HashMap,
+    collections::{BTreeMap, BTreeSet, HashMap},
+    fs,
     path::PathBuf,
     sync::{Arc, Mutex},
-    time::Duration,
+    time::{Duration, Instant},
 };
 pub const IMAGE_EXTENSIONS: [&str; 6] = ["jpg", "jpeg", "png", "webp", "gif", "avif"];
 pub const MAX_EMBEDS: usize = 10;
 pub const MAX_ATTACHMENTS: usize = 10;
-pub const MESSAGE_DELAY: Duration = Duration::from_millis(100);
-pub type FileIndex = HashMap<String, Vec<PathBuf>>;
+pub const PROGRESS_INTERVAL: Duration = Duration::from_secs(3);
+pub const PAUSE_POLL_INTERVAL: Duration = Duration::from_secs(1);
+pub const MAX_MESSAGES_PER_MINUTE: u32 = 6000;
+pub const QUEUE_POLL_INTERVAL: Duration = Duration::from_secs(2);
+pub const MAX_UPLOAD_SIZE: u64 = 10 * 1024 * 1024;
+pub const MAX_MESSAGE_LENGTH: usize = 2000;
+pub const MAX_DESCRIPTION_LENGTH: usize = 4096;
+pub const MAX_FIELD_LENGTH: usize = 1024;
+pub const MAX_BUTTONS_PER_ROW: usize = 5;
+pub const MAX_ACTION_ROWS: usize = 5;
+pub const DRY_RUN_PREVIEW_COUNT: usize = 3;
+pub const REFRESH_LOOKAHEAD: usize = 25;
+pub const MAX_REPORT_ITEMS: usize = 10;
+pub const ESTIMATED_SEND_TIME: Duration = Duration::from_secs(1);
+pub const ESTIMATED_REACTION_TIME: Duration = Duration::from_millis(350);
+pub const REACTION_BUTTON_PREFIX: &str = "reaction_";
+pub const MORE_REACTIONS_KEY: &str = "more";
+pub const IMPORT_CONTROL_PREFIX: &str = "import_";
+pub const PROGRESS_BAR_WIDTH: usize = 20;
+pub const MAX_FINISHED_JOBS: usize = 50;
+pub const MAX_LISTED_JOBS: usize = 20;
+pub const DEFAULT_MAX_IMPORTS: usize = 2;
+pub const DEFAULT_MAX_GUILD_IMPORTS: usize = 1;
+pub const LEGACY_REACTION_BUTTON_PREFIX: &str = "dummy_reaction_";
+pub const REACTION_STORE_PATH: &str = "reactions.jsonl";
+pub const JOB_STORE_PATH: &str = "jobs.json";
+pub const IMPORT_REPORT_NAME: &str = "import-report.json";
+pub type SharedZip = Arc<Mutex<zip::ZipArchive<fs::File>>>;
+#[derive(Clone)]
+pub enum MediaFile {
+    Disk(PathBuf),
+    Zip(SharedZip, String),
+}
+#[derive(Default)]
+pub struct FileIndex {
+    pub files: HashMap<String, Vec<MediaFile>>,
+    pub ids: HashMap<String, Vec<MediaFile>>,
+    pub hashes: HashMap<String, Vec<MediaFile>>,
+    pub _download: Option<tempfile::NamedTempFile>,
+}
 pub type Error = Box<dyn std::error::Error + Send + Sync>;
 pub type Context<'a> = poise::Context<'a, Data, Error>;
 #[derive(Default)]
 pub struct Data {
-    pub cancellation_flags: Arc<Mutex<HashMap<serenity::ChannelId, bool>>>,
+    pub jobs: Arc<Mutex<JobRegistry>>,
+    pub reaction_store: Arc<Mutex<HashMap<serenity::MessageId, Vec<StoredReaction>>>>,
+    pub scheduler: Arc<Scheduler>,
+    pub http_client: reqwest::Client,
+}
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq)]
+pub enum JobState {
+    Queued,
+    Running,
+    Paused,
+    Cancelled,
+    Done,
+    Failed,
+    Interrupted,
+}
+#[derive(Serialize, Deserialize)]
+pub struct Job {
+    pub id: u64,
+    pub state: JobState,
+    pub owner: serenity::UserId,
+    pub guild_id: Option<serenity::GuildId>,
+    pub channel_id: serenity::ChannelId,
+    pub arguments: Vec<String>,
+    pub priority: usize,
+    pub start_at: Option<serenity::Timestamp>,
+    pub total: Option<usize>,
+    pub done: usize,
+    pub failures: usize,
+    pub created: serenity::Timestamp,
+    pub error: Option<String>,
+}
+#[derive(Serialize, Deserialize, Default)]
+pub struct JobRegistry {
+    pub next_id: u64,
+    pub jobs: BTreeMap<u64, Job>,
+    #[serde(skip)]
+    pub version: u64,
+}
+pub struct ImportProgress {
+    pub total: Option<usize>,
+    pub done: usize,
+    pub failures: usize,
+    pub skipped: usize,
+    pub current_timestamp: Option<i64>,
+    pub started: Instant,
+    pub paused_for: Duration,
 }
-#[derive(Deserialize)]
-#[serde(rename_all = "camelCase")]
 pub struct Export {
     pub guild: GuildInfo,
     pub channel: ChannelInfo,
-    pub messages: Vec<MessageInfo>,
 }
 #[derive(Deserialize)]
 pub struct GuildInfo {
@@ -37,6 +119,7 @@ pub struct ChannelInfo {
 #[derive(Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct MessageInfo {
+    pub id: String,
     pub content: String,
     pub author: Author,
     pub timestamp: String,
@@ -48,16 +131,25 @@ pub struct MessageInfo {
     pub reactions: Vec<ReactionInfo>,
 }
 #[derive(Deserialize)]
+pub struct MessageHeader {
+    pub id: String,
+    pub timestamp: String,
+}
+#[derive(Deserialize, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct Author {
-    pub id: serenity::UserId,
+    pub id: Option<serenity::UserId>,
     pub name: String,
     pub avatar_url: String,
     pub color: Option<String>,
+    #[serde(default)]
+    pub is_bot: bool,
 }
 #[derive(Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct AttachmentInfo {
+    #[serde(default)]
+    pub id: String,
     pub url: String,
     pub file_name: String,
 }
@@ -99,17 +191,100 @@ pub struct ImportOptions {
     pub disable_button: bool,
     pub accent_color: bool,
     pub current_avatar: bool,
+    pub reaction_counts: bool,
     pub range_start: Option<usize>,
     pub range_end: Option<usize>,
     pub first: Option<usize>,
     pub last: Option<usize>,
+    pub source_channel: Option<String>,
+    pub guild_name: Option<String>,
+    pub channel_name: Option<String>,
+    pub from_id: Option<String>,
+    pub to_id: Option<String>,
+    pub from_time: Option<serenity::Timestamp>,
+    pub to_time: Option<serenity::Timestamp>,
+    pub authors: Vec<String>,
+    pub excluded_authors: Vec<String>,
+    pub after: Option<serenity::Timestamp>,
+    pub before: Option<serenity::Timestamp>,
+    pub contains: Option<regex::Regex>,
+    pub has: Vec<MessageContent>,
+    pub skip_bots: bool,
+    pub dry_run: bool,
+    pub priority: usize,
+    pub start_at: Option<serenity::Timestamp>,
+    pub messages_per_minute: Option<u32>,
+    pub quiet_hours: Option<(u32, u32)>,
+}
+#[derive(Clone, Copy, PartialEq)]
+pub enum MessageContent {
+    Attachment,
+    Image,
+    Reaction,
+}
+pub enum MediaMatch {
+    Found(MediaFile),
+    Ambiguous(usize),
+    Missing,
 }
 pub enum MediaSource {
-    Local(PathBuf, String),
+    Local(MediaFile, String),
+    Fetched(Vec<u8>, String),
     Remote(String),
+    Unavailable(String),
+}
+#[derive(Default)]
+pub struct DryRunReport {
+    pub messages: usize,
+    pub sends: usize,
+    pub reactions: usize,
+    pub lookups: usize,
+    pub local_media: usize,
+    pub remote_media: usize,
+    pub expired_media: usize,
+    pub missing_media: usize,
+    pub oversized_files: Vec<String>,
+    pub unknown_emojis: BTreeSet<String>,
+    pub long_messages: Vec<String>,
+}
+#[derive(Default)]
+pub struct Delivery {
+    pub ephemeral: bool,
+    pub sent: usize,
+    pub errors: Vec<String>,
+    pub remote_media: Vec<String>,
+    pub missing_media: Vec<String>,
+}
+#[derive(Serialize)]
+pub struct ReportEntry {
+    pub message_id: String,
+    pub timestamp: String,
+    pub status: &'static str,
+    pub detail: String,
+}
+#[derive(Default)]
+pub struct ImportSummary {
+    pub sent: usize,
+    pub partial: usize,
+    pub failed: usize,
+    pub skipped: usize,
+    pub missing_media: usize,
+    pub remote_media: usize,
+    pub entries: Vec<ReportEntry>,
 }
 pub struct MessageBatch {
     pub attachments: Vec<serenity::CreateAttachment>,
     pub embeds: Vec<serenity::CreateEmbed>,
     pub count: usize,
 }
+#[derive(Serialize, Deserialize, Clone)]
+pub struct StoredReaction {
+    pub emoji: String,
+    pub count: u64,
+    pub users: Vec<String>,
+}
+#[derive(Serialize, Deserialize)]
+pub struct StoredReactions {
+    pub message_id: serenity::MessageId,
+    pub reactions: Vec<StoredReaction>,
+}
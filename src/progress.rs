@@ -0,0 +1,99 @@
+use crate::models::*;
+use crate::utils::*;
+use poise::serenity_prelude::{self as serenity};
+use std::time::Instant;
+fn progress_bar(done: usize, total: usize) -> String {
+    let filled = (done * PROGRESS_BAR_WIDTH)
+        .checked_div(total)
+        .unwrap_or(PROGRESS_BAR_WIDTH)
+        .min(PROGRESS_BAR_WIDTH);
+    format!(
+        "{}{}",
+        "\u{2588}".repeat(filled),
+        "\u{2591}".repeat(PROGRESS_BAR_WIDTH - filled)
+    )
+}
+impl ImportProgress {
+    pub fn new(total: Option<usize>) -> Self {
+        Self {
+            total,
+            done: 0,
+            failures: 0,
+            skipped: 0,
+            current_timestamp: None,
+            started: Instant::now(),
+            paused_for: Default::default(),
+        }
+    }
+    pub fn advance(&mut self, message: &MessageInfo, failures: usize) {
+        self.done += 1;
+        self.failures = failures;
+        self.current_timestamp = serenity::Timestamp::parse(&message.timestamp)
+            .ok()
+            .map(|timestamp| timestamp.unix_timestamp());
+    }
+    fn remaining_time(&self) -> Option<std::time::Duration> {
+        let processed = self.done.saturating_sub(self.skipped);
+        if processed == 0 {
+            return None;
+        }
+        let elapsed = self.started.elapsed().saturating_sub(self.paused_for);
+        let remaining = self.total?.saturating_sub(self.done) as u32;
+        Some(elapsed / processed as u32 * remaining)
+    }
+}
+pub fn format_progress(progress: &ImportProgress, status: &str) -> String {
+    let mut lines = vec![format!("**{status}**")];
+    match progress.total {
+        Some(total) => {
+            let percent = (progress.done * 100).checked_div(total).unwrap_or(100);
+            lines.push(format!(
+                "`{}` {percent}% ({}/{total})",
+                progress_bar(progress.done, total),
+                progress.done
+            ));
+        }
+        None => lines.push(format!("{} messages", progress.done)),
+    }
+    if let Some(timestamp) = progress.current_timestamp {
+        lines.push(format!("Current message: <t:{timestamp}:f>"));
+    }
+    if let Some(remaining) = progress
+        .remaining_time()
+        .filter(|_| progress.total.is_some_and(|total| progress.done < total))
+    {
+        lines.push(format!("ETA: {}", format_duration(remaining)));
+    }
+    lines.push(format!("Failures: {}", progress.failures));
+    lines.join("\n")
+}
+pub fn job_status(job_id: u64, status: &str) -> String {
+    format!("Job #{job_id}: {status}")
+}
+pub fn progress_buttons(job_id: u64, state: JobState) -> Vec<serenity::CreateActionRow> {
+    let button = |action: &str, label: &str, style: serenity::ButtonStyle| {
+        serenity::CreateButton::new(format!("{IMPORT_CONTROL_PREFIX}{action}_{job_id}"))
+            .label(label)
+            .style(style)
+    };
+    let toggle = match state {
+        JobState::Paused => button("resume", "Resume", serenity::ButtonStyle::Success),
+        _ => button("pause", "Pause", serenity::ButtonStyle::Secondary),
+    };
+    vec![serenity::CreateActionRow::Buttons(vec![
+        toggle,
+        button("cancel", "Cancel", serenity::ButtonStyle::Danger),
+    ])]
+}
+pub fn parse_import_control(custom_id: &str) -> Option<(JobState, u64)> {
+    let (action, job_id) = custom_id
+        .strip_prefix(IMPORT_CONTROL_PREFIX)?
+        .split_once('_')?;
+    let state = match action {
+        "pause" => JobState::Paused,
+        "resume" => JobState::Running,
+        "cancel" => JobState::Cancelled,
+        _ => return None,
+    };
+    Some((state, job_id.parse().ok()?))
+}
@@ -0,0 +1,74 @@
+use crate::models::*;
+use crate::utils::*;
+use poise::serenity_prelude::{self as serenity};
+pub fn note_media(delivery: &mut Delivery, sources: &[MediaSource]) {
+    for source in sources {
+        match source {
+            MediaSource::Remote(url) if is_url(url) => delivery.remote_media.push(url.clone()),
+            MediaSource::Remote(path) => delivery.missing_media.push(file_name_from_url(path)),
+            MediaSource::Unavailable(filename) => delivery.missing_media.push(filename.clone()),
+            MediaSource::Local(..) | MediaSource::Fetched(..) => {}
+        }
+    }
+}
+pub fn record_outcome(summary: &mut ImportSummary, message: &MessageInfo, delivery: Delivery) {
+    let (status, count) = match (delivery.sent, delivery.errors.is_empty()) {
+        (0, true) => ("skipped", &mut summary.skipped),
+        (_, true) => ("sent", &mut summary.sent),
+        (0, false) => ("failed", &mut summary.failed),
+        (_, false) => ("partially sent", &mut summary.partial),
+    };
+    *count += 1;
+    let mut problems: Vec<(&'static str, String)> = delivery
+        .errors
+        .into_iter()
+        .map(|error| (status, error))
+        .collect();
+    if delivery.sent == 0 && problems.is_empty() {
+        problems.push((status, "nothing to send".to_string()));
+    }
+    summary.remote_media += delivery.remote_media.len();
+    summary.missing_media += delivery.missing_media.len();
+    problems.extend(
+        delivery
+            .remote_media
+            .into_iter()
+            .map(|url| ("media remote only", url)),
+    );
+    problems.extend(
+        delivery
+            .missing_media
+            .into_iter()
+            .map(|filename| ("media missing", filename)),
+    );
+    summary
+        .entries
+        .extend(problems.into_iter().map(|(status, detail)| ReportEntry {
+            message_id: message.id.clone(),
+            timestamp: message.timestamp.clone(),
+            status,
+            detail,
+        }));
+}
+pub fn summary_embed(summary: &ImportSummary) -> serenity::CreateEmbed {
+    let fields = [
+        ("Sent", summary.sent),
+        ("Partially sent", summary.partial),
+        ("Failed", summary.failed),
+        ("Skipped", summary.skipped),
+        ("Media missing", summary.missing_media),
+        ("Media remote only", summary.remote_media),
+    ];
+    serenity::CreateEmbed::new().title("Import summary").fields(
+        fields
+            .into_iter()
+            .map(|(name, count)| (name, count.to_string(), true)),
+    )
+}
+pub fn report_attachment(summary: &ImportSummary) -> Option<serenity::CreateAttachment> {
+    if summary.entries.is_empty() {
+        return None;
+    }
+    let data = serde_json::to_vec_pretty(&summary.entries).ok()?;
+    Some(serenity::CreateAttachment::bytes(data, IMPORT_REPORT_NAME))
+}
@@ -0,0 +1,145 @@
+use poise::serenity_prelude::{self as serenity};
+use std::{
+    collections::{hash_map::RandomState, HashMap},
+    future::Future,
+    hash::{BuildHasher, Hasher},
+    sync::Mutex,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+use tokio::time;
+const MAX_SEND_ATTEMPTS: u32 = 5;
+const SEND_TIMEOUT: Duration = Duration::from_secs(120);
+const BACKOFF_BASE: Duration = Duration::from_millis(500);
+const MAX_BACKOFF: Duration = Duration::from_secs(30);
+const MAX_PACING_DELAY: Duration = Duration::from_secs(5);
+const PACING_STEP: Duration = Duration::from_millis(250);
+#[derive(Default)]
+pub struct Scheduler {
+    delays: Mutex<HashMap<u64, Duration>>,
+}
+pub struct SendFailure {
+    pub message: String,
+    pub transient: bool,
+    pub retry_after: Option<Duration>,
+}
+fn is_transient(error: &serenity::Error) -> bool {
+    match error {
+        serenity::Error::Http(serenity::HttpError::UnsuccessfulRequest(response)) => {
+            let status = response.status_code.as_u16();
+            status == 429 || status >= 500
+        }
+        serenity::Error::Http(serenity::HttpError::Request(e)) => e.is_timeout() || e.is_connect(),
+        _ => false,
+    }
+}
+impl From<serenity::Error> for SendFailure {
+    fn from(error: serenity::Error) -> Self {
+        Self {
+            transient: is_transient(&error),
+            message: error.to_string(),
+            retry_after: None,
+        }
+    }
+}
+fn jitter(limit: Duration) -> Duration {
+    let random = RandomState::new().build_hasher().finish();
+    Duration::from_millis(random % (limit.as_millis() as u64 + 1))
+}
+fn backoff_delay(attempt: u32) -> Duration {
+    let delay = BACKOFF_BASE.saturating_mul(1 << attempt).min(MAX_BACKOFF);
+    delay / 2 + jitter(delay / 2)
+}
+fn route_channel(path: &str) -> Option<u64> {
+    let (_, rest) = path.split_once("/channels/")?;
+    rest.split('/').next()?.parse().ok()
+}
+pub fn message_nonce() -> serenity::Nonce {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_nanos();
+    let random = RandomState::new().build_hasher().finish();
+    serenity::Nonce::String(format!("{:x}{:x}", nanos as u64, random >> 32))
+}
+impl Scheduler {
+    fn delay(&self, route: u64) -> Duration {
+        let delays = self.delays.lock().unwrap();
+        delays.get(&route).copied().unwrap_or_default()
+    }
+    fn slow_down(&self, route: u64) {
+        let mut delays = self.delays.lock().unwrap();
+        let delay = delays.entry(route).or_default();
+        *delay = (*delay * 2).max(PACING_STEP).min(MAX_PACING_DELAY);
+    }
+    fn speed_up(&self, route: u64) {
+        let mut delays = self.delays.lock().unwrap();
+        if let Some(delay) = delays.get_mut(&route) {
+            *delay = delay.saturating_sub(*delay / 4 + Duration::from_millis(10));
+            if delay.is_zero() {
+                delays.remove(&route);
+            }
+        }
+    }
+    pub fn note_rate_limit(&self, info: &serenity::RatelimitInfo) {
+        if let Some(channel_id) = route_channel(&info.path) {
+            self.slow_down(channel_id);
+        }
+    }
+    pub async fn run<T, E, F, Fut>(&self, route: u64, operation: F) -> Result<T, String>
+    where
+        E: Into<SendFailure>,
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T, E>>,
+    {
+        self.run_attempts(route, MAX_SEND_ATTEMPTS, operation).await
+    }
+    pub async fn run_once<T, E, F, Fut>(&self, route: u64, operation: F) -> Result<T, String>
+    where
+        E: Into<SendFailure>,
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T, E>>,
+    {
+        self.run_attempts(route, 1, operation).await
+    }
+    async fn run_attempts<T, E, F, Fut>(
+        &self,
+        route: u64,
+        max_attempts: u32,
+        mut operation: F,
+    ) -> Result<T, String>
+    where
+        E: Into<SendFailure>,
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T, E>>,
+    {
+        let mut attempt = 0;
+        loop {
+            let delay = self.delay(route);
+            if !delay.is_zero() {
+                time::sleep(delay).await;
+            }
+            let failure = match time::timeout(SEND_TIMEOUT, operation()).await {
+                Ok(Ok(value)) => {
+                    self.speed_up(route);
+                    return Ok(value);
+                }
+                Ok(Err(e)) => e.into(),
+                Err(_) => SendFailure {
+                    message: "Request timed out".to_string(),
+                    transient: true,
+                    retry_after: None,
+                },
+            };
+            attempt += 1;
+            if !failure.transient || attempt >= max_attempts {
+                return Err(failure.message);
+            }
+            self.slow_down(route);
+            let wait = failure
+                .retry_after
+                .unwrap_or_else(|| backoff_delay(attempt))
+                .min(MAX_BACKOFF);
+            time::sleep(wait).await;
+        }
+    }
+}
@@ -0,0 +1,360 @@
+use crate::models::*;
+use crate::utils::*;
+use serde::Deserialize;
+use std::{
+    collections::{BTreeSet, HashMap},
+    fs,
+    io::Read,
+    path::Path,
+    sync::LazyLock,
+};
+static LINK_PATTERN: LazyLock<regex::Regex> =
+    LazyLock::new(|| regex::Regex::new(r"<([^<>|]+)(?:\|([^<>]*))?>").expect("valid regex"));
+static BOLD_PATTERN: LazyLock<regex::Regex> =
+    LazyLock::new(|| regex::Regex::new(r"(^|[\s(])\*([^*\n]+)\*").expect("valid regex"));
+static STRIKE_PATTERN: LazyLock<regex::Regex> =
+    LazyLock::new(|| regex::Regex::new(r"(^|[\s(])~([^~\n]+)~").expect("valid regex"));
+static EMOJI_PATTERN: LazyLock<regex::Regex> =
+    LazyLock::new(|| regex::Regex::new(r":([a-z0-9_+\-]+):").expect("valid regex"));
+static DAY_FILE_PATTERN: LazyLock<regex::Regex> =
+    LazyLock::new(|| regex::Regex::new(r"^([^/]+)/\d{4}-\d{2}-\d{2}\.json$").expect("valid regex"));
+const SLACK_EMOJIS: [(&str, &str); 40] = [
+    ("+1", "👍"),
+    ("thumbsup", "👍"),
+    ("-1", "👎"),
+    ("thumbsdown", "👎"),
+    ("heart", "❤️"),
+    ("joy", "😂"),
+    ("laughing", "😆"),
+    ("smile", "😄"),
+    ("slightly_smiling_face", "🙂"),
+    ("grinning", "😀"),
+    ("wink", "😉"),
+    ("sweat_smile", "😅"),
+    ("rolling_on_the_floor_laughing", "🤣"),
+    ("thinking_face", "🤔"),
+    ("open_mouth", "😮"),
+    ("cry", "😢"),
+    ("sob", "😭"),
+    ("rage", "😡"),
+    ("eyes", "👀"),
+    ("tada", "🎉"),
+    ("fire", "🔥"),
+    ("rocket", "🚀"),
+    ("star", "⭐"),
+    ("sparkles", "✨"),
+    ("100", "💯"),
+    ("pray", "🙏"),
+    ("clap", "👏"),
+    ("raised_hands", "🙌"),
+    ("muscle", "💪"),
+    ("wave", "👋"),
+    ("ok_hand", "👌"),
+    ("point_up", "☝️"),
+    ("white_check_mark", "✅"),
+    ("heavy_check_mark", "✔️"),
+    ("x", "❌"),
+    ("warning", "⚠️"),
+    ("bulb", "💡"),
+    ("memo", "📝"),
+    ("coffee", "☕"),
+    ("smiley", "😃"),
+];
+const SKIPPED_SUBTYPES: [&str; 7] = [
+    "channel_join",
+    "channel_leave",
+    "channel_topic",
+    "channel_purpose",
+    "channel_name",
+    "channel_archive",
+    "pinned_item",
+];
+#[derive(Deserialize, Default)]
+#[serde(default)]
+struct SlackUser {
+    id: String,
+    name: String,
+    color: Option<String>,
+    is_bot: bool,
+    profile: SlackProfile,
+}
+#[derive(Deserialize, Default)]
+#[serde(default)]
+struct SlackProfile {
+    display_name: String,
+    real_name: String,
+    image_72: Option<String>,
+    image_512: Option<String>,
+}
+#[derive(Deserialize, Default)]
+#[serde(default)]
+struct SlackMessage {
+    subtype: Option<String>,
+    user: Option<String>,
+    username: Option<String>,
+    user_profile: Option<SlackProfile>,
+    text: String,
+    ts: String,
+    thread_ts: Option<String>,
+    edited: Option<SlackEdited>,
+    files: Vec<SlackFile>,
+    reactions: Vec<SlackReaction>,
+}
+#[derive(Deserialize, Default)]
+#[serde(default)]
+struct SlackEdited {
+    ts: String,
+}
+#[derive(Deserialize, Default)]
+#[serde(default)]
+struct SlackFile {
+    name: Option<String>,
+    url_private_download: Option<String>,
+    url_private: Option<String>,
+}
+#[derive(Deserialize, Default)]
+#[serde(default)]
+struct SlackReaction {
+    name: String,
+    users: Vec<String>,
+    count: u64,
+}
+fn slack_emoji(name: &str) -> Option<&'static str> {
+    let name = name.split("::").next().unwrap_or(name);
+    SLACK_EMOJIS
+        .iter()
+        .find(|(code, _)| *code == name)
+        .map(|(_, emoji)| *emoji)
+}
+fn slack_timestamp(ts: &str) -> String {
+    let seconds = ts.split('.').next().unwrap_or(ts);
+    unix_timestamp(seconds.parse().unwrap_or(0))
+}
+fn profile_name(profile: &SlackProfile) -> Option<&str> {
+    [profile.display_name.as_str(), profile.real_name.as_str()]
+        .into_iter()
+        .find(|name| !name.is_empty())
+}
+fn user_name(user: &SlackUser) -> String {
+    profile_name(&user.profile)
+        .unwrap_or(&user.name)
+        .to_string()
+}
+fn convert_text(text: &str, users: &HashMap<String, SlackUser>) -> String {
+    let text = LINK_PATTERN.replace_all(text, |c: &regex::Captures| {
+        let target = &c[1];
+        let label = c
+            .get(2)
+            .map(|m| m.as_str())
+            .filter(|label| !label.is_empty());
+        if let Some(id) = target.strip_prefix('@') {
+            let name = users.get(id).map(user_name);
+            format!("@{}", name.as_deref().or(label).unwrap_or(id))
+        } else if let Some(id) = target.strip_prefix('#') {
+            format!("#{}", label.unwrap_or(id))
+        } else if let Some(command) = target.strip_prefix('!') {
+            format!(
+                "@{}",
+                label.unwrap_or(command.split('^').next().unwrap_or(command))
+            )
+        } else {
+            match label {
+                Some(label) if label != target => format!("[{label}]({target})"),
+                _ => target.to_string(),
+            }
+        }
+    });
+    let text = BOLD_PATTERN.replace_all(&text, "$1**$2**");
+    let text = STRIKE_PATTERN.replace_all(&text, "$1~~$2~~");
+    let text = EMOJI_PATTERN.replace_all(&text, |c: &regex::Captures| {
+        slack_emoji(&c[1]).map_or_else(|| c[0].to_string(), str::to_string)
+    });
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&amp;", "&")
+}
+fn read_entry<T: serde::de::DeserializeOwned>(
+    archive: &mut zip::ZipArchive<fs::File>,
+    name: &str,
+) -> Result<T, String> {
+    let mut entry = archive
+        .by_name(name)
+        .map_err(|e| format!("Error reading Slack export {name}: {e}"))?;
+    let mut text = String::new();
+    entry
+        .read_to_string(&mut text)
+        .map_err(|e| format!("Error reading Slack export {name}: {e}"))?;
+    serde_json::from_str(&text).map_err(|e| format!("Error parsing Slack export {name}: {e}"))
+}
+fn select_channel(channels: &BTreeSet<String>, channel: Option<&str>) -> Result<String, String> {
+    if let Some(channel) = channel {
+        let channel = channel.trim_start_matches('#');
+        return channels
+            .iter()
+            .find(|name| name.eq_ignore_ascii_case(channel))
+            .cloned()
+            .ok_or_else(|| format!("Slack channel {channel} not found in export"));
+    }
+    if channels.len() == 1 {
+        return Ok(channels.iter().next().cloned().unwrap_or_default());
+    }
+    Err(format!(
+        "Slack export has several channels, pick one with --source-channel: {}",
+        channels.iter().cloned().collect::<Vec<_>>().join(", ")
+    ))
+}
+fn convert_message(message: SlackMessage, users: &HashMap<String, SlackUser>) -> MessageInfo {
+    let user = message.user.as_deref().and_then(|id| users.get(id));
+    let name = user
+        .map(user_name)
+        .or_else(|| {
+            message
+                .user_profile
+                .as_ref()
+                .and_then(profile_name)
+                .map(str::to_string)
+        })
+        .or(message.username)
+        .or(message.user)
+        .unwrap_or_else(|| "Unknown".to_string());
+    let avatar_url = user
+        .map(|user| &user.profile)
+        .or(message.user_profile.as_ref())
+        .and_then(|profile| profile.image_512.clone().or(profile.image_72.clone()))
+        .unwrap_or_default();
+    let mut reactions = Vec::new();
+    for reaction in message.reactions {
+        let Some(emoji) = slack_emoji(&reaction.name) else {
+            continue;
+        };
+        let reaction_users = reaction
+            .users
+            .iter()
+            .map(|id| users.get(id).map_or_else(|| id.clone(), user_name))
+            .map(|name| serde_json::json!({ "name": name }))
+            .collect();
+        reactions.push(ReactionInfo {
+            emoji: EmojiInfo {
+                name: emoji.to_string(),
+                code: reaction.name,
+                ..Default::default()
+            },
+            count: reaction.count.max(1).into(),
+            users: reaction_users,
+        });
+    }
+    MessageInfo {
+        id: message.ts.clone(),
+        content: convert_text(&message.text, users),
+        author: Author {
+            id: None,
+            name,
+            avatar_url,
+            color: user
+                .and_then(|user| user.color.as_ref())
+                .map(|color| format!("#{color}")),
+            is_bot: user.is_some_and(|user| user.is_bot)
+                || message.subtype.as_deref() == Some("bot_message"),
+        },
+        timestamp: slack_timestamp(&message.ts),
+        timestamp_edited: message.edited.map(|edited| slack_timestamp(&edited.ts)),
+        attachments: message
+            .files
+            .into_iter()
+            .filter_map(|file| {
+                let url = file.url_private_download.or(file.url_private)?;
+                let file_name = file.name.unwrap_or_else(|| file_name_from_url(&url));
+                Some(AttachmentInfo {
+                    id: String::new(),
+                    url,
+                    file_name,
+                })
+            })
+            .collect(),
+        mentions: Vec::new(),
+        inline_emojis: Vec::new(),
+        reactions,
+    }
+}
+pub fn is_slack_export(path: &Path) -> bool {
+    let Ok(file) = fs::File::open(path) else {
+        return false;
+    };
+    zip::ZipArchive::new(file).is_ok_and(|archive| {
+        archive
+            .file_names()
+            .any(|name| matches!(name, "users.json" | "channels.json"))
+    })
+}
+pub fn parse_slack_export(
+    path: &Path,
+    channel: Option<&str>,
+) -> Result<(Export, Vec<MessageInfo>), String> {
+    let file = fs::File::open(path).map_err(|e| format!("Error opening zip: {e}"))?;
+    let mut archive = zip::ZipArchive::new(file).map_err(|e| format!("Error reading zip: {e}"))?;
+    let entries: Vec<String> = archive.file_names().map(str::to_string).collect();
+    let channels: BTreeSet<String> = entries
+        .iter()
+        .filter_map(|name| DAY_FILE_PATTERN.captures(name).map(|c| c[1].to_string()))
+        .collect();
+    let channel = select_channel(&channels, channel)?;
+    let users: HashMap<String, SlackUser> =
+        read_entry::<Vec<SlackUser>>(&mut archive, "users.json")
+            .unwrap_or_default()
+            .into_iter()
+            .map(|user| (user.id.clone(), user))
+            .collect();
+    let mut days: Vec<&String> = entries
+        .iter()
+        .filter(|name| {
+            DAY_FILE_PATTERN
+                .captures(name)
+                .is_some_and(|c| c[1] == channel)
+        })
+        .collect();
+    days.sort();
+    let mut slack_messages = Vec::new();
+    for day in days {
+        slack_messages.extend(read_entry::<Vec<SlackMessage>>(&mut archive, day)?);
+    }
+    slack_messages.retain(|message| {
+        !message
+            .subtype
+            .as_deref()
+            .is_some_and(|subtype| SKIPPED_SUBTYPES.contains(&subtype))
+    });
+    slack_messages.sort_by(|a, b| {
+        let ts = |message: &SlackMessage| message.ts.parse::<f64>().unwrap_or(0.0);
+        ts(a).total_cmp(&ts(b))
+    });
+    let mut messages: Vec<MessageInfo> = Vec::new();
+    let mut positions = HashMap::new();
+    for slack_message in slack_messages {
+        let thread_ts = slack_message
+            .thread_ts
+            .clone()
+            .filter(|thread_ts| *thread_ts != slack_message.ts);
+        let mut message = convert_message(slack_message, &users);
+        if let Some(parent) = thread_ts.and_then(|ts| positions.get(&ts).map(|&i| &messages[i])) {
+            let parent: &MessageInfo = parent;
+            message.content = format!(
+                "{}{}",
+                reply_quote(&parent.author.name, &parent.content),
+                message.content
+            );
+        }
+        positions.insert(message.id.clone(), messages.len());
+        messages.push(message);
+    }
+    let workspace = extract_export_name(&path.to_string_lossy());
+    Ok((
+        Export {
+            guild: GuildInfo { name: workspace },
+            channel: ChannelInfo {
+                name: channel,
+                category: None,
+            },
+        },
+        messages,
+    ))
+}
@@ -0,0 +1,113 @@
+use crate::archive::*;
+use crate::csv::*;
+use crate::html::*;
+use crate::matrix::*;
+use crate::models::*;
+use crate::slack::*;
+use crate::telegram::*;
+use crate::text::*;
+use crate::utils::*;
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+pub trait SourceAdapter: Sync {
+    fn detect(&self, path: &Path) -> bool;
+    fn load(
+        &self,
+        path: &Path,
+        source_channel: Option<&str>,
+    ) -> Result<(Export, Vec<MessageInfo>), String>;
+    fn media_dir(&self, _path: &Path) -> Option<PathBuf> {
+        None
+    }
+}
+struct HtmlSource;
+struct CsvSource;
+struct TextSource;
+struct SlackSource;
+struct TelegramSource;
+struct MatrixSource;
+pub const SOURCE_ADAPTERS: [&dyn SourceAdapter; 6] = [
+    &HtmlSource,
+    &CsvSource,
+    &TextSource,
+    &SlackSource,
+    &TelegramSource,
+    &MatrixSource,
+];
+fn read_text(path: &Path) -> Result<String, String> {
+    fs::read_to_string(path).map_err(|e| format!("Error reading export file: {e}"))
+}
+fn export_name(path: &Path) -> String {
+    extract_export_name(&path.to_string_lossy())
+}
+impl SourceAdapter for HtmlSource {
+    fn detect(&self, path: &Path) -> bool {
+        is_html_file(&path.to_string_lossy())
+    }
+    fn load(&self, path: &Path, _: Option<&str>) -> Result<(Export, Vec<MessageInfo>), String> {
+        parse_html_export(&read_text(path)?)
+    }
+}
+impl SourceAdapter for CsvSource {
+    fn detect(&self, path: &Path) -> bool {
+        is_csv_file(&path.to_string_lossy())
+    }
+    fn load(&self, path: &Path, _: Option<&str>) -> Result<(Export, Vec<MessageInfo>), String> {
+        parse_csv_export(&read_text(path)?, &export_name(path))
+    }
+}
+impl SourceAdapter for TextSource {
+    fn detect(&self, path: &Path) -> bool {
+        is_text_file(&path.to_string_lossy())
+    }
+    fn load(&self, path: &Path, _: Option<&str>) -> Result<(Export, Vec<MessageInfo>), String> {
+        parse_text_export(&read_text(path)?, &export_name(path))
+    }
+}
+impl SourceAdapter for SlackSource {
+    fn detect(&self, path: &Path) -> bool {
+        detect_archive(path) == Some(ArchiveKind::Zip) && is_slack_export(path)
+    }
+    fn load(
+        &self,
+        path: &Path,
+        source_channel: Option<&str>,
+    ) -> Result<(Export, Vec<MessageInfo>), String> {
+        parse_slack_export(path, source_channel)
+    }
+}
+impl SourceAdapter for TelegramSource {
+    fn detect(&self, path: &Path) -> bool {
+        matches!(json_first_key(path).as_deref(), Some("name" | "about"))
+    }
+    fn load(
+        &self,
+        path: &Path,
+        source_channel: Option<&str>,
+    ) -> Result<(Export, Vec<MessageInfo>), String> {
+        parse_telegram_export(path, source_channel)
+    }
+    fn media_dir(&self, path: &Path) -> Option<PathBuf> {
+        let dir = path.parent().filter(|dir| !dir.as_os_str().is_empty());
+        Some(dir.unwrap_or(Path::new(".")).to_path_buf())
+    }
+}
+impl SourceAdapter for MatrixSource {
+    fn detect(&self, path: &Path) -> bool {
+        json_first_key(path).as_deref() == Some("room_name")
+    }
+    fn load(&self, path: &Path, _: Option<&str>) -> Result<(Export, Vec<MessageInfo>), String> {
+        parse_matrix_export(path)
+    }
+}
+pub fn find_source_adapter(path: &Path) -> Option<&'static dyn SourceAdapter> {
+    SOURCE_ADAPTERS
+        .into_iter()
+        .find(|adapter| adapter.detect(path))
+}
+pub fn default_media_dir(path: &Path) -> Option<String> {
+    let dir = find_source_adapter(path)?.media_dir(path)?;
+    Some(dir.to_string_lossy().into_owned())
+}
@@ -0,0 +1,479 @@
+use crate::download::*;
+use crate::models::*;
+use crate::source::*;
+use crate::utils::*;
+use poise::serenity_prelude::{self as serenity};
+use serde::de::{self, DeserializeSeed, IgnoredAny, MapAccess, SeqAccess, Visitor};
+use std::{
+    collections::VecDeque,
+    fmt, fs,
+    io::BufReader,
+    path::{Path, PathBuf},
+};
+use tokio::sync::{mpsc, oneshot};
+const STREAM_BUFFER: usize = 64;
+pub struct ExportFiles {
+    pub paths: Vec<PathBuf>,
+    pub name: String,
+    _download: Option<tempfile::NamedTempFile>,
+}
+pub struct ExportStream {
+    pub export: Export,
+    pub messages: MessageStream,
+}
+pub struct MessageStream(mpsc::Receiver<Result<MessageInfo, String>>);
+impl MessageStream {
+    pub async fn next_message(&mut self) -> Option<Result<MessageInfo, String>> {
+        self.0.recv().await
+    }
+}
+struct ExportSeed {
+    header: Option<oneshot::Sender<Export>>,
+    messages: mpsc::Sender<Result<MessageInfo, String>>,
+}
+impl<'de> DeserializeSeed<'de> for ExportSeed {
+    type Value = ();
+    fn deserialize<D: de::Deserializer<'de>>(self, deserializer: D) -> Result<(), D::Error> {
+        deserializer.deserialize_map(self)
+    }
+}
+impl<'de> Visitor<'de> for ExportSeed {
+    type Value = ();
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a DiscordChatExporter JSON export")
+    }
+    fn visit_map<A: MapAccess<'de>>(mut self, mut map: A) -> Result<(), A::Error> {
+        let mut guild = None;
+        let mut channel = None;
+        let mut found_messages = false;
+        while let Some(key) = map.next_key::<String>()? {
+            match key.as_str() {
+                "guild" => guild = Some(map.next_value()?),
+                "channel" => channel = Some(map.next_value()?),
+                "messages" => {
+                    let (Some(guild), Some(channel)) = (guild.take(), channel.take()) else {
+                        return Err(de::Error::custom(
+                            "expected guild and channel before messages",
+                        ));
+                    };
+                    if let Some(header) = self.header.take() {
+                        let _ = header.send(Export { guild, channel });
+                    }
+                    map.next_value_seed(MessagesSeed(&self.messages))?;
+                    found_messages = true;
+                }
+                _ => {
+                    map.next_value::<IgnoredAny>()?;
+                }
+            }
+        }
+        if !found_messages {
+            return Err(de::Error::missing_field("messages"));
+        }
+        Ok(())
+    }
+}
+struct MessagesSeed<'a>(&'a mpsc::Sender<Result<MessageInfo, String>>);
+impl<'de> DeserializeSeed<'de> for MessagesSeed<'_> {
+    type Value = ();
+    fn deserialize<D: de::Deserializer<'de>>(self, deserializer: D) -> Result<(), D::Error> {
+        deserializer.deserialize_seq(self)
+    }
+}
+impl<'de> Visitor<'de> for MessagesSeed<'_> {
+    type Value = ();
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("an array of messages")
+    }
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<(), A::Error> {
+        while let Some(message) = seq.next_element::<MessageInfo>()? {
+            if self.0.blocking_send(Ok(message)).is_err() {
+                return Err(de::Error::custom("import stopped"));
+            }
+        }
+        Ok(())
+    }
+}
+fn part_sort_key(path: &Path) -> (String, usize) {
+    let stem = path.file_stem().and_then(|s| s.to_str()).unwrap_or("");
+    let (base, number) = split_part_suffix(stem);
+    (base.to_string(), number)
+}
+pub fn find_export_parts(json_path: &str) -> Result<Vec<PathBuf>, String> {
+    let path = Path::new(json_path);
+    let dir = path
+        .parent()
+        .filter(|parent| !parent.as_os_str().is_empty())
+        .unwrap_or(Path::new("."));
+    let file_name = path
+        .file_name()
+        .and_then(|s| s.to_str())
+        .ok_or_else(|| format!("Error reading JSON file: {json_path} not found"))?;
+    let mut parts = Vec::new();
+    if is_glob(file_name) {
+        let entries = fs::read_dir(dir).map_err(|e| format!("Error reading directory: {e}"))?;
+        for entry in entries.filter_map(Result::ok) {
+            let is_file = entry.file_type().is_ok_and(|ft| ft.is_file());
+            if is_file && matches_wildcard(file_name, &entry.file_name().to_string_lossy()) {
+                parts.push(entry.path());
+            }
+        }
+        parts.sort_by_key(|part| part_sort_key(part));
+    } else {
+        if !path.is_file() {
+            return Err(format!("Error reading JSON file: {json_path} not found"));
+        }
+        parts.push(path.to_path_buf());
+        let (base, first) = part_sort_key(path);
+        let extension = path
+            .extension()
+            .and_then(|s| s.to_str())
+            .map(|ext| format!(".{ext}"))
+            .unwrap_or_default();
+        for number in first + 1.. {
+            let next = dir.join(format!("{base} [part {number}]{extension}"));
+            if !next.is_file() {
+                break;
+            }
+            parts.push(next);
+        }
+    }
+    if parts.is_empty() {
+        return Err(format!("No export files match {json_path}"));
+    }
+    Ok(parts)
+}
+pub async fn resolve_export_files(
+    client: &reqwest::Client,
+    json_path: &str,
+    progress: Option<&ProgressSender>,
+) -> Result<ExportFiles, String> {
+    if is_url(json_path) {
+        let download = download_to_tempfile(client, json_path, "export", progress).await?;
+        return Ok(ExportFiles {
+            paths: vec![download.file.path().to_path_buf()],
+            name: extract_export_name(&download.file_name),
+            _download: Some(download.file),
+        });
+    }
+    let paths = find_export_parts(json_path)?;
+    let name = extract_export_name(&paths[0].to_string_lossy());
+    Ok(ExportFiles {
+        paths,
+        name,
+        _download: None,
+    })
+}
+fn send_parsed(
+    parsed: (Export, Vec<MessageInfo>),
+    header: Option<oneshot::Sender<Export>>,
+    messages: &mpsc::Sender<Result<MessageInfo, String>>,
+) -> Result<(), String> {
+    let (export, parsed_messages) = parsed;
+    if let Some(header) = header {
+        let _ = header.send(export);
+    }
+    for message in parsed_messages {
+        if messages.blocking_send(Ok(message)).is_err() {
+            break;
+        }
+    }
+    Ok(())
+}
+fn parse_part(
+    path: &Path,
+    source_channel: Option<&str>,
+    header: Option<oneshot::Sender<Export>>,
+    messages: &mpsc::Sender<Result<MessageInfo, String>>,
+) -> Result<(), String> {
+    if let Some(adapter) = find_source_adapter(path) {
+        return send_parsed(adapter.load(path, source_channel)?, header, messages);
+    }
+    let file = fs::File::open(path).map_err(|e| format!("Error reading JSON file: {e}"))?;
+    let mut deserializer = serde_json::Deserializer::from_reader(BufReader::new(file));
+    let seed = ExportSeed {
+        header,
+        messages: messages.clone(),
+    };
+    seed.deserialize(&mut deserializer)
+        .and_then(|_| deserializer.end())
+        .map_err(|e| format!("Error parsing JSON: {e}"))
+}
+pub async fn open_export(
+    paths: &[PathBuf],
+    source_channel: Option<&str>,
+) -> Result<ExportStream, String> {
+    let (header_sender, header_receiver) = oneshot::channel();
+    let (message_sender, mut message_receiver) = mpsc::channel(STREAM_BUFFER);
+    let paths = paths.to_vec();
+    let source_channel = source_channel.map(str::to_string);
+    tokio::task::spawn_blocking(move || {
+        let mut header = Some(header_sender);
+        for path in &paths {
+            if let Err(e) = parse_part(
+                path,
+                source_channel.as_deref(),
+                header.take(),
+                &message_sender,
+            ) {
+                let e = if paths.len() > 1 {
+                    format!("{e} in {}", path.display())
+                } else {
+                    e
+                };
+                let _ = message_sender.blocking_send(Err(e));
+                return;
+            }
+        }
+    });
+    match header_receiver.await {
+        Ok(export) => Ok(ExportStream {
+            export,
+            messages: MessageStream(message_receiver),
+        }),
+        Err(_) => Err(match message_receiver.recv().await {
+            Some(Err(e)) => e,
+            _ => "Error parsing JSON: missing guild or channel".to_string(),
+        }),
+    }
+}
+fn matches_filters(message: &MessageInfo, options: &ImportOptions) -> bool {
+    if options.skip_bots && message.author.is_bot {
+        return false;
+    }
+    if !options.authors.is_empty()
+        && !options
+            .authors
+            .iter()
+            .any(|query| matches_author(&message.author, query))
+    {
+        return false;
+    }
+    if options
+        .excluded_authors
+        .iter()
+        .any(|query| matches_author(&message.author, query))
+    {
+        return false;
+    }
+    if options.after.is_some()
+        || options.before.is_some()
+        || options.from_time.is_some()
+        || options.to_time.is_some()
+    {
+        let Ok(timestamp) = serenity::Timestamp::parse(&message.timestamp) else {
+            return false;
+        };
+        if options.after.is_some_and(|after| timestamp <= after)
+            || options.before.is_some_and(|before| timestamp >= before)
+            || options.from_time.is_some_and(|from| timestamp < from)
+            || options.to_time.is_some_and(|to| timestamp > to)
+        {
+            return false;
+        }
+    }
+    if let Some(pattern) = &options.contains {
+        if !pattern.is_match(&message.content) {
+            return false;
+        }
+    }
+    options
+        .has
+        .iter()
+        .all(|content| has_content(message, *content))
+}
+pub struct MessageSelector<'a> {
+    options: &'a ImportOptions,
+    started: bool,
+    reached_end_id: bool,
+    finished: bool,
+    draining: bool,
+    index: usize,
+    last_messages: VecDeque<MessageInfo>,
+}
+impl<'a> MessageSelector<'a> {
+    pub fn new(options: &'a ImportOptions) -> Self {
+        Self {
+            options,
+            started: options.from_id.is_none(),
+            reached_end_id: false,
+            finished: false,
+            draining: false,
+            index: 0,
+            last_messages: VecDeque::new(),
+        }
+    }
+    pub async fn next(
+        &mut self,
+        stream: &mut MessageStream,
+    ) -> Result<Option<MessageInfo>, String> {
+        loop {
+            if self.draining {
+                return Ok(self.last_messages.pop_front());
+            }
+            let next = if self.finished {
+                None
+            } else {
+                stream.next_message().await
+            };
+            match next {
+                Some(message) => {
+                    if let Some(selected) = self.push(message?)? {
+                        return Ok(Some(selected));
+                    }
+                }
+                None => {
+                    self.check_ids()?;
+                    self.draining = true;
+                }
+            }
+        }
+    }
+    fn push(&mut self, message: MessageInfo) -> Result<Option<MessageInfo>, String> {
+        let options = self.options;
+        if !self.started {
+            if options.from_id.as_deref() == Some(message.id.as_str()) {
+                self.started = true;
+            } else {
+                if options.to_id.as_deref() == Some(message.id.as_str()) {
+                    return Err("--from-id must not come after --to-id".to_string());
+                }
+                return Ok(None);
+            }
+        }
+        if options.to_id.as_deref() == Some(message.id.as_str()) {
+            self.reached_end_id = true;
+            self.finished = true;
+        }
+        if !matches_filters(&message, options) {
+            return Ok(None);
+        }
+        let index = self.index;
+        self.index += 1;
+        if let (Some(start), Some(end)) = (options.range_start, options.range_end) {
+            if start <= end {
+                if index >= end {
+                    self.finished = true;
+                }
+                return Ok((index >= start && index <= end).then_some(message));
+            }
+        } else if let Some(n) = options.first.filter(|n| *n > 0) {
+            if index + 1 >= n {
+                self.finished = true;
+            }
+            return Ok((index < n).then_some(message));
+        } else if let Some(n) = options.last.filter(|n| *n > 0) {
+            if self.last_messages.len() == n {
+                self.last_messages.pop_front();
+            }
+            self.last_messages.push_back(message);
+            return Ok(None);
+        }
+        Ok(Some(message))
+    }
+    fn check_ids(&self) -> Result<(), String> {
+        if let Some(id) = self.options.from_id.as_ref().filter(|_| !self.started) {
+            return Err(format!("Message ID {id} is not present in the export."));
+        }
+        if let Some(id) = self
+            .options
+            .to_id
+            .as_ref()
+            .filter(|_| !self.finished && !self.reached_end_id)
+        {
+            return Err(format!("Message ID {id} is not present in the export."));
+        }
+        Ok(())
+    }
+}
+struct CountSeed<'a, 'b>(&'a mut MessageSelector<'b>, &'a mut usize);
+impl<'de> DeserializeSeed<'de> for CountSeed<'_, '_> {
+    type Value = ();
+    fn deserialize<D: de::Deserializer<'de>>(self, deserializer: D) -> Result<(), D::Error> {
+        deserializer.deserialize_map(self)
+    }
+}
+impl<'de> Visitor<'de> for CountSeed<'_, '_> {
+    type Value = ();
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a DiscordChatExporter JSON export")
+    }
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<(), A::Error> {
+        while let Some(key) = map.next_key::<String>()? {
+            if key == "messages" {
+                map.next_value_seed(HeadersSeed(&mut *self.0, &mut *self.1))?;
+            } else {
+                map.next_value::<IgnoredAny>()?;
+            }
+        }
+        Ok(())
+    }
+}
+struct HeadersSeed<'a, 'b>(&'a mut MessageSelector<'b>, &'a mut usize);
+impl<'de> DeserializeSeed<'de> for HeadersSeed<'_, '_> {
+    type Value = ();
+    fn deserialize<D: de::Deserializer<'de>>(self, deserializer: D) -> Result<(), D::Error> {
+        deserializer.deserialize_seq(self)
+    }
+}
+impl<'de> Visitor<'de> for HeadersSeed<'_, '_> {
+    type Value = ();
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("an array of messages")
+    }
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<(), A::Error> {
+        while let Some(header) = seq.next_element::<MessageHeader>()? {
+            if self.0.finished {
+                continue;
+            }
+            let message = MessageInfo {
+                id: header.id,
+                content: String::new(),
+                author: Author {
+                    id: None,
+                    name: String::new(),
+                    avatar_url: String::new(),
+                    color: None,
+                    is_bot: false,
+                },
+                timestamp: header.timestamp,
+                timestamp_edited: None,
+                attachments: Vec::new(),
+                mentions: Vec::new(),
+                inline_emojis: Vec::new(),
+                reactions: Vec::new(),
+            };
+            if self.0.push(message).map_err(de::Error::custom)?.is_some() {
+                *self.1 += 1;
+            }
+        }
+        Ok(())
+    }
+}
+fn counts_from_headers(paths: &[PathBuf], options: &ImportOptions) -> bool {
+    !options.skip_bots
+        && options.authors.is_empty()
+        && options.excluded_authors.is_empty()
+        && options.contains.is_none()
+        && options.has.is_empty()
+        && paths.iter().all(|path| find_source_adapter(path).is_none())
+}
+pub fn count_messages(paths: &[PathBuf], options: &ImportOptions) -> Result<Option<usize>, String> {
+    if !counts_from_headers(paths, options) {
+        return Ok(None);
+    }
+    tokio::task::block_in_place(|| {
+        let mut selector = MessageSelector::new(options);
+        let mut count = 0;
+        for path in paths {
+            let file = fs::File::open(path).map_err(|e| format!("Error reading JSON file: {e}"))?;
+            let mut deserializer = serde_json::Deserializer::from_reader(BufReader::new(file));
+            CountSeed(&mut selector, &mut count)
+                .deserialize(&mut deserializer)
+                .and_then(|_| deserializer.end())
+                .map_err(|e| format!("Error parsing JSON: {e}"))?;
+        }
+        selector.check_ids()?;
+        Ok(Some(count + selector.last_messages.len()))
+    })
+}
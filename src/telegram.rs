@@ -0,0 +1,217 @@
+use crate::models::*;
+use crate::utils::*;
+use serde::Deserialize;
+use std::{collections::HashMap, fs, io::BufReader, path::Path};
+const MISSING_FILE_PREFIX: &str = "(File not included";
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum TelegramExport {
+    Account { chats: TelegramChatList },
+    Chat(TelegramChat),
+}
+#[derive(Deserialize)]
+struct TelegramChatList {
+    list: Vec<TelegramChat>,
+}
+#[derive(Deserialize)]
+struct TelegramChat {
+    name: Option<String>,
+    messages: Vec<TelegramMessage>,
+}
+#[derive(Deserialize, Default)]
+#[serde(default)]
+struct TelegramMessage {
+    id: i64,
+    #[serde(rename = "type")]
+    kind: String,
+    date: String,
+    date_unixtime: Option<String>,
+    edited_unixtime: Option<String>,
+    from: Option<String>,
+    forwarded_from: Option<String>,
+    reply_to_message_id: Option<i64>,
+    text: serde_json::Value,
+    text_entities: Vec<TelegramEntity>,
+    photo: Option<String>,
+    file: Option<String>,
+    sticker_emoji: Option<String>,
+    reactions: Vec<TelegramReaction>,
+}
+#[derive(Deserialize, Default)]
+#[serde(default)]
+struct TelegramEntity {
+    #[serde(rename = "type")]
+    kind: String,
+    text: String,
+    href: Option<String>,
+}
+#[derive(Deserialize, Default)]
+#[serde(default)]
+struct TelegramReaction {
+    emoji: Option<String>,
+    document_id: Option<String>,
+    count: u64,
+    recent: Vec<TelegramReactionUser>,
+}
+#[derive(Deserialize, Default)]
+#[serde(default)]
+struct TelegramReactionUser {
+    from: Option<String>,
+}
+fn render_entity(entity: &TelegramEntity) -> String {
+    let text = &entity.text;
+    match entity.kind.as_str() {
+        "bold" => format!("**{text}**"),
+        "italic" => format!("*{text}*"),
+        "underline" => format!("__{text}__"),
+        "strikethrough" => format!("~~{text}~~"),
+        "spoiler" => format!("||{text}||"),
+        "code" => format!("`{text}`"),
+        "pre" => format!("```\n{text}\n```"),
+        "blockquote" => text
+            .lines()
+            .map(|line| format!("> {line}"))
+            .collect::<Vec<_>>()
+            .join("\n"),
+        "text_link" => match &entity.href {
+            Some(href) => format!("[{text}]({href})"),
+            None => text.clone(),
+        },
+        _ => text.clone(),
+    }
+}
+fn render_text(message: &TelegramMessage) -> String {
+    if !message.text_entities.is_empty() {
+        return message.text_entities.iter().map(render_entity).collect();
+    }
+    match &message.text {
+        serde_json::Value::String(text) => text.clone(),
+        serde_json::Value::Array(parts) => parts
+            .iter()
+            .filter_map(|part| match part {
+                serde_json::Value::String(text) => Some(text.clone()),
+                _ => part.get("text")?.as_str().map(str::to_string),
+            })
+            .collect(),
+        _ => String::new(),
+    }
+}
+fn telegram_timestamp(unixtime: Option<&str>, date: &str) -> String {
+    match unixtime.and_then(|seconds| seconds.parse().ok()) {
+        Some(seconds) => unix_timestamp(seconds),
+        None => normalize_timestamp(date),
+    }
+}
+fn select_chat(chats: Vec<TelegramChat>, chat: Option<&str>) -> Result<TelegramChat, String> {
+    let chat_name = |candidate: &TelegramChat| candidate.name.clone().unwrap_or_default();
+    if let Some(chat) = chat {
+        let names: Vec<String> = chats.iter().map(chat_name).collect();
+        return chats
+            .into_iter()
+            .find(|candidate| chat_name(candidate).eq_ignore_ascii_case(chat))
+            .ok_or_else(|| {
+                format!(
+                    "Telegram chat {chat} not found in export, available: {}",
+                    names.join(", ")
+                )
+            });
+    }
+    if chats.len() == 1 {
+        return Ok(chats.into_iter().next().expect("one chat"));
+    }
+    Err(format!(
+        "Telegram export has several chats, pick one with --source-channel: {}",
+        chats.iter().map(chat_name).collect::<Vec<_>>().join(", ")
+    ))
+}
+fn convert_message(message: TelegramMessage) -> MessageInfo {
+    let mut content = render_text(&message);
+    if let Some(emoji) = message
+        .sticker_emoji
+        .as_ref()
+        .filter(|_| content.is_empty())
+    {
+        content = emoji.clone();
+    }
+    if let Some(forwarded_from) = &message.forwarded_from {
+        content = format!("*Forwarded from {forwarded_from}*\n{content}");
+    }
+    let mut reactions = Vec::new();
+    for reaction in &message.reactions {
+        let Some(name) = reaction.emoji.clone().or(reaction.document_id.clone()) else {
+            continue;
+        };
+        reactions.push(ReactionInfo {
+            emoji: EmojiInfo {
+                code: name.clone(),
+                name,
+                ..Default::default()
+            },
+            count: reaction.count.max(1).into(),
+            users: reaction
+                .recent
+                .iter()
+                .filter_map(|user| user.from.as_ref())
+                .map(|name| serde_json::json!({ "name": name }))
+                .collect(),
+        });
+    }
+    MessageInfo {
+        id: message.id.to_string(),
+        content,
+        author: plain_author(None, message.from.as_deref().unwrap_or("Deleted Account")),
+        timestamp: telegram_timestamp(message.date_unixtime.as_deref(), &message.date),
+        timestamp_edited: message
+            .edited_unixtime
+            .as_deref()
+            .map(|seconds| telegram_timestamp(Some(seconds), "")),
+        attachments: [message.photo, message.file]
+            .into_iter()
+            .flatten()
+            .filter(|path| !path.starts_with(MISSING_FILE_PREFIX))
+            .map(|path| AttachmentInfo {
+                id: String::new(),
+                file_name: file_name_from_url(&path),
+                url: path,
+            })
+            .collect(),
+        mentions: Vec::new(),
+        inline_emojis: Vec::new(),
+        reactions,
+    }
+}
+pub fn parse_telegram_export(
+    path: &Path,
+    chat: Option<&str>,
+) -> Result<(Export, Vec<MessageInfo>), String> {
+    let file = fs::File::open(path).map_err(|e| format!("Error reading JSON file: {e}"))?;
+    let export: TelegramExport = serde_json::from_reader(BufReader::new(file))
+        .map_err(|e| format!("Error parsing Telegram export: {e}"))?;
+    let chat = match export {
+        TelegramExport::Account { chats } => select_chat(chats.list, chat)?,
+        TelegramExport::Chat(single) => single,
+    };
+    let mut messages: Vec<MessageInfo> = Vec::new();
+    let mut positions = HashMap::new();
+    for telegram_message in chat.messages {
+        if telegram_message.kind != "message" {
+            continue;
+        }
+        let reply_to = telegram_message.reply_to_message_id;
+        let mut message = convert_message(telegram_message);
+        if let Some(parent) = reply_to.and_then(|id| positions.get(&id.to_string())) {
+            let parent: &MessageInfo = &messages[*parent];
+            message.content = format!(
+                "{}{}",
+                reply_quote(&parent.author.name, &parent.content),
+                message.content
+            );
+        }
+        positions.insert(message.id.clone(), messages.len());
+        messages.push(message);
+    }
+    Ok((
+        plain_export(chat.name.as_deref().unwrap_or_default()),
+        messages,
+    ))
+}
@@ -0,0 +1,108 @@
+use crate::models::*;
+use crate::utils::*;
+use poise::serenity_prelude::{self as serenity};
+#[derive(PartialEq)]
+enum Section {
+    Content,
+    Attachments,
+    Reactions,
+    Other,
+}
+fn parse_message_header(line: &str) -> Option<(String, String)> {
+    let rest = line.strip_prefix('[')?;
+    let (date, author) = rest.split_once("] ")?;
+    let author = author.trim_end_matches(" (pinned)");
+    if author.is_empty() {
+        return None;
+    }
+    let timestamp = normalize_timestamp(date);
+    serenity::Timestamp::parse(&timestamp).ok()?;
+    Some((timestamp, author.to_string()))
+}
+fn finish_message(message: Option<MessageInfo>, messages: &mut Vec<MessageInfo>) {
+    if let Some(mut message) = message {
+        message.content = message.content.trim_end().to_string();
+        messages.push(message);
+    }
+}
+pub fn parse_text_export(
+    text: &str,
+    export_name: &str,
+) -> Result<(Export, Vec<MessageInfo>), String> {
+    let mut export = plain_export(export_name);
+    let mut lines = text.trim_start_matches('\u{FEFF}').lines().peekable();
+    if lines.peek().is_some_and(|line| line.starts_with("====")) {
+        lines.next();
+        for line in lines.by_ref() {
+            if line.starts_with("====") {
+                break;
+            }
+            if let Some(guild) = line.strip_prefix("Guild: ") {
+                export.guild.name = guild.trim().to_string();
+            } else if let Some(channel) = line.strip_prefix("Channel: ") {
+                match channel.trim().rsplit_once(" / ") {
+                    Some((category, name)) => {
+                        export.channel.category = Some(category.to_string());
+                        export.channel.name = name.to_string();
+                    }
+                    None => export.channel.name = channel.trim().to_string(),
+                }
+            }
+        }
+    }
+    let mut messages = Vec::new();
+    let mut current: Option<MessageInfo> = None;
+    let mut section = Section::Content;
+    for line in lines {
+        if line.starts_with("====") {
+            break;
+        }
+        if let Some((timestamp, author)) = parse_message_header(line) {
+            finish_message(current.take(), &mut messages);
+            current = Some(MessageInfo {
+                id: String::new(),
+                content: String::new(),
+                author: plain_author(None, &author),
+                timestamp,
+                timestamp_edited: None,
+                attachments: Vec::new(),
+                mentions: Vec::new(),
+                inline_emojis: Vec::new(),
+                reactions: Vec::new(),
+            });
+            section = Section::Content;
+            continue;
+        }
+        let Some(message) = current.as_mut() else {
+            continue;
+        };
+        if line.starts_with("Exported ") && line.ends_with(" message(s)") {
+            continue;
+        }
+        match line.trim() {
+            "{Attachments}" => section = Section::Attachments,
+            "{Reactions}" => section = Section::Reactions,
+            "{Embed}" | "{Stickers}" => section = Section::Other,
+            trimmed => match section {
+                Section::Content if !message.content.is_empty() || !trimmed.is_empty() => {
+                    message.content.push_str(line);
+                    message.content.push('\n');
+                }
+                Section::Attachments if !trimmed.is_empty() => {
+                    message.attachments.push(AttachmentInfo {
+                        id: String::new(),
+                        url: trimmed.to_string(),
+                        file_name: file_name_from_url(trimmed),
+                    });
+                }
+                Section::Reactions => message.reactions.extend(parse_reaction_counts(trimmed)),
+                _ => {}
+            },
+        }
+    }
+    finish_message(current, &mut messages);
+    if messages.is_empty() && export.guild.name.is_empty() {
+        return Err("Error parsing text: not a DiscordChatExporter text export".to_string());
+    }
+    Ok((export, messages))
+}
@@ -1,11 +1,28 @@
+use crate::archive::*;
+use crate::download::*;
 use crate::models::*;
 use poise::serenity_prelude::{self as serenity};
+use sha2::{Digest, Sha256};
 use std::{
-    collections::HashSet,
+    collections::{HashMap, HashSet},
     fs,
-    io::{self, Write},
+    io::{self, Read, Write},
     path::{Path, PathBuf},
+    sync::{Arc, LazyLock, Mutex},
+    time::Duration,
 };
+static ISO_TIMESTAMP_PATTERN: LazyLock<regex::Regex> = LazyLock::new(|| {
+    regex::Regex::new(r"^(\d{4})-(\d{2})-(\d{2})[ T](\d{1,2}):(\d{2})(?::(\d{2}))?$")
+        .expect("valid regex")
+});
+static US_TIMESTAMP_PATTERN: LazyLock<regex::Regex> = LazyLock::new(|| {
+    regex::Regex::new(
+        r"^(\d{1,2})/(\d{1,2})/(\d{4}),? (\d{1,2}):(\d{2})(?::(\d{2}))? ?([AaPp][Mm])?$",
+    )
+    .expect("valid regex")
+});
+static REACTION_COUNT_PATTERN: LazyLock<regex::Regex> =
+    LazyLock::new(|| regex::Regex::new(r"([^\s,]+) \((\d+)\)").expect("valid regex"));
 const PADDING: &str =
     "\u{2060}\u{200A}\u{2060}\u{200A}\u{2060}\u{200A}\u{2060}\u{200A}\u{2060}\u{200A}\u{2060}";
 pub fn is_image_file(filename: &str) -> bool {
@@ -19,151 +36,496 @@ pub fn parse_color(hex: &str) -> Option<u32> {
 pub fn is_url(path: &str) -> bool {
     path.starts_with("http://") || path.starts_with("https://")
 }
+pub fn url_host_in(url: &str, hosts: &str) -> bool {
+    let host = reqwest::Url::parse(url)
+        .ok()
+        .and_then(|url| url.host_str().map(str::to_ascii_lowercase))
+        .unwrap_or_default();
+    hosts
+        .split(',')
+        .map(|allowed| allowed.trim().to_ascii_lowercase())
+        .any(|allowed| {
+            !allowed.is_empty() && (host == allowed || host.ends_with(&format!(".{allowed}")))
+        })
+}
+pub fn parse_time_bound(value: &str) -> Result<serenity::Timestamp, String> {
+    if let Ok(snowflake) = value.parse::<u64>() {
+        if snowflake == 0 {
+            return Err(format!("Invalid message ID: {value}"));
+        }
+        return Ok(serenity::MessageId::new(snowflake).created_at());
+    }
+    parse_date_time(value).ok_or_else(|| format!("Invalid date or message ID: {value}"))
+}
+pub fn parse_date_time(value: &str) -> Option<serenity::Timestamp> {
+    let timestamp = if value.len() == 10 {
+        format!("{value}T00:00:00Z")
+    } else {
+        value.to_string()
+    };
+    serenity::Timestamp::parse(&timestamp).ok()
+}
+pub fn parse_quiet_hours(value: &str) -> Result<(u32, u32), String> {
+    let error = || format!("Invalid quiet hours: {value}");
+    let (start, end) = value.split_once('-').ok_or_else(error)?;
+    let hour = |hour: &str| hour.trim().parse::<u32>().ok().filter(|&hour| hour < 24);
+    match (hour(start), hour(end)) {
+        (Some(start), Some(end)) if start != end => Ok((start, end)),
+        _ => Err(error()),
+    }
+}
+pub fn quiet_hours_remaining(quiet_hours: (u32, u32), now: i64) -> Option<Duration> {
+    let (start, end) = (quiet_hours.0 as i64 * 3600, quiet_hours.1 as i64 * 3600);
+    let time_of_day = now.rem_euclid(86400);
+    let is_quiet = if start < end {
+        (start..end).contains(&time_of_day)
+    } else {
+        time_of_day >= start || time_of_day < end
+    };
+    is_quiet.then(|| Duration::from_secs((end - time_of_day).rem_euclid(86400) as u64))
+}
+pub fn snowflake_timestamp(id: &str) -> Option<String> {
+    let snowflake = id.parse::<u64>().ok().filter(|id| *id > 0)?;
+    Some(serenity::MessageId::new(snowflake).created_at().to_string())
+}
+pub fn is_html_file(filename: &str) -> bool {
+    let filename = filename.to_ascii_lowercase();
+    filename.ends_with(".html") || filename.ends_with(".htm")
+}
+pub fn is_csv_file(filename: &str) -> bool {
+    filename.to_ascii_lowercase().ends_with(".csv")
+}
+pub fn is_text_file(filename: &str) -> bool {
+    filename.to_ascii_lowercase().ends_with(".txt")
+}
+pub fn file_name_from_url(url: &str) -> String {
+    url.split(['?', '#'])
+        .next()
+        .unwrap_or(url)
+        .rsplit(['/', '\\'])
+        .next()
+        .unwrap_or(url)
+        .to_string()
+}
+pub fn normalize_timestamp(date: &str) -> String {
+    let date = date.trim();
+    if serenity::Timestamp::parse(date).is_ok() {
+        return date.to_string();
+    }
+    if let Some(c) = ISO_TIMESTAMP_PATTERN.captures(date) {
+        let second = c.get(6).map_or("00", |m| m.as_str());
+        return format!(
+            "{}-{}-{}T{:0>2}:{}:{second}Z",
+            &c[1], &c[2], &c[3], &c[4], &c[5]
+        );
+    }
+    if let Some(c) = US_TIMESTAMP_PATTERN.captures(date) {
+        let mut hour: u32 = c[4].parse().unwrap_or(0);
+        match c.get(7).map(|m| m.as_str().to_ascii_uppercase()).as_deref() {
+            Some("PM") if hour < 12 => hour += 12,
+            Some("AM") if hour == 12 => hour = 0,
+            _ => {}
+        }
+        let second = c.get(6).map_or("00", |m| m.as_str());
+        return format!(
+            "{}-{:0>2}-{:0>2}T{hour:02}:{}:{second}Z",
+            &c[3], &c[1], &c[2], &c[5]
+        );
+    }
+    date.to_string()
+}
+pub fn parse_reaction_counts(text: &str) -> Vec<ReactionInfo> {
+    REACTION_COUNT_PATTERN
+        .captures_iter(text)
+        .map(|c| ReactionInfo {
+            emoji: EmojiInfo {
+                name: c[1].to_string(),
+                code: c[1].to_string(),
+                ..Default::default()
+            },
+            count: c[2].parse::<u64>().unwrap_or(1).into(),
+            users: Vec::new(),
+        })
+        .collect()
+}
+pub fn unix_timestamp(seconds: i64) -> String {
+    serenity::Timestamp::from_unix_timestamp(seconds)
+        .map(|timestamp| timestamp.to_string())
+        .unwrap_or_default()
+}
+pub fn json_first_key(path: &Path) -> Option<String> {
+    let mut prefix = [0; 4096];
+    let read = fs::File::open(path).ok()?.read(&mut prefix).ok()?;
+    let text = String::from_utf8_lossy(&prefix[..read]);
+    let rest = text
+        .trim_start_matches('\u{FEFF}')
+        .trim_start()
+        .strip_prefix('{')?
+        .trim_start()
+        .strip_prefix('"')?;
+    rest.split_once('"').map(|(key, _)| key.to_string())
+}
+pub fn reply_quote(author: &str, content: &str) -> String {
+    let snippet: String = content
+        .lines()
+        .find(|line| !line.trim().is_empty())
+        .unwrap_or("")
+        .chars()
+        .take(100)
+        .collect();
+    if snippet.is_empty() {
+        format!("> **{author}**\n")
+    } else {
+        format!("> **{author}**: {snippet}\n")
+    }
+}
+pub fn add_reaction(reactions: &mut Vec<ReactionInfo>, emoji: EmojiInfo, user: Option<&str>) {
+    let user = user.map(|name| serde_json::json!({ "name": name }));
+    if let Some(reaction) = reactions.iter_mut().find(|r| r.emoji.name == emoji.name) {
+        reaction.count = (get_reaction_count(reaction) + 1).into();
+        reaction.users.extend(user);
+        return;
+    }
+    reactions.push(ReactionInfo {
+        emoji,
+        count: 1.into(),
+        users: user.into_iter().collect(),
+    });
+}
+pub fn plain_export(export_name: &str) -> Export {
+    Export {
+        guild: GuildInfo {
+            name: String::new(),
+        },
+        channel: ChannelInfo {
+            name: export_name.to_string(),
+            category: None,
+        },
+    }
+}
+pub fn plain_author(id: Option<&str>, name: &str) -> Author {
+    Author {
+        id: id
+            .and_then(|id| id.trim().parse::<u64>().ok())
+            .filter(|id| *id > 0)
+            .map(serenity::UserId::new),
+        name: name.trim().to_string(),
+        avatar_url: String::new(),
+        color: None,
+        is_bot: false,
+    }
+}
+pub fn matches_author(author: &Author, query: &str) -> bool {
+    author.id.is_some_and(|id| id.to_string() == query) || author.name.eq_ignore_ascii_case(query)
+}
+pub fn has_content(message: &MessageInfo, content: MessageContent) -> bool {
+    match content {
+        MessageContent::Attachment => !message.attachments.is_empty(),
+        MessageContent::Image => message
+            .attachments
+            .iter()
+            .any(|attachment| is_image_file(&attachment.file_name)),
+        MessageContent::Reaction => !message.reactions.is_empty(),
+    }
+}
+pub fn split_part_suffix(stem: &str) -> (&str, usize) {
+    if let Some((base, number)) = stem
+        .strip_suffix(']')
+        .and_then(|rest| rest.rsplit_once(" [part "))
+    {
+        if let Ok(number) = number.parse() {
+            return (base, number);
+        }
+    }
+    (stem, 1)
+}
+pub fn is_glob(path: &str) -> bool {
+    path.contains(['*', '?'])
+}
+pub fn matches_wildcard(pattern: &str, name: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let name: Vec<char> = name.chars().collect();
+    let (mut p, mut n) = (0, 0);
+    let mut backtrack = None;
+    while n < name.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == name[n]) {
+            p += 1;
+            n += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            backtrack = Some((p, n));
+            p += 1;
+        } else if let Some((star, matched)) = backtrack {
+            p = star + 1;
+            n = matched + 1;
+            backtrack = Some((star, matched + 1));
+        } else {
+            return false;
+        }
+    }
+    pattern[p..].iter().all(|c| *c == '*')
+}
 pub fn extract_export_name(json_path: &str) -> String {
     let last_segment = if is_url(json_path) {
-        json_path.rsplit('/').next().unwrap_or("")
+        file_name_from_url(json_path)
     } else {
-        json_path
+        json_path.to_string()
     };
-    Path::new(last_segment)
+    let stem = Path::new(&last_segment)
         .file_stem()
         .and_then(|s| s.to_str())
-        .unwrap_or("")
-        .to_string()
+        .unwrap_or("");
+    split_part_suffix(stem).0.to_string()
+}
+fn snowflakes(text: &str) -> impl Iterator<Item = &str> {
+    text.split(|c: char| !c.is_ascii_digit())
+        .filter(|token| (17..=20).contains(&token.len()))
+}
+fn url_hash_in_name(file_name: &str) -> Option<String> {
+    let stem = file_name
+        .rsplit_once('.')
+        .map_or(file_name, |(stem, _)| stem);
+    let is_hex = |text: &str| text.chars().all(|c| c.is_ascii_hexdigit());
+    if stem.len() == 64 && is_hex(stem) {
+        return Some(stem.to_ascii_lowercase());
+    }
+    let (_, hash) = stem.rsplit_once('-')?;
+    (hash.len() == 5 && is_hex(hash)).then(|| hash.to_ascii_lowercase())
+}
+fn add_media_file(index: &mut FileIndex, path: &str, file: MediaFile) {
+    let mut components = path.rsplit(['/', '\\']);
+    let file_name = components.next().unwrap_or(path).to_ascii_lowercase();
+    let parent = components.next().unwrap_or("");
+    let ids: HashSet<&str> = snowflakes(&file_name).chain(snowflakes(parent)).collect();
+    for id in ids {
+        index
+            .ids
+            .entry(id.to_string())
+            .or_default()
+            .push(file.clone());
+    }
+    if let Some(hash) = url_hash_in_name(&file_name) {
+        index.hashes.entry(hash).or_default().push(file.clone());
+    }
+    index.files.entry(file_name).or_default().push(file);
 }
 pub fn scan_files(paths: &[PathBuf]) -> FileIndex {
-    let mut index = FileIndex::new();
+    let mut index = FileIndex::default();
     for root in paths {
         for entry in walkdir::WalkDir::new(root)
             .into_iter()
             .filter_map(Result::ok)
             .filter(|e| e.file_type().is_file())
         {
-            index
-                .entry(entry.file_name().to_string_lossy().to_ascii_lowercase())
-                .or_default()
-                .push(entry.path().to_path_buf());
+            let path = entry.path().to_string_lossy().into_owned();
+            add_media_file(
+                &mut index,
+                &path,
+                MediaFile::Disk(entry.path().to_path_buf()),
+            );
         }
     }
     index
 }
-pub fn locate_media_dirs(media_root: &Path, export_name: &str) -> Vec<PathBuf> {
-    let has_subdirs = fs::read_dir(media_root).ok().map_or(false, |mut dir| {
-        dir.any(|e| {
-            e.ok()
-                .map_or(false, |de| de.file_type().map_or(false, |ft| ft.is_dir()))
-        })
-    });
+fn media_dirs(has_subdirs: bool, is_dir: impl Fn(&str) -> bool, export_name: &str) -> Vec<String> {
     if !has_subdirs {
-        return vec![media_root.to_path_buf()];
+        return vec![String::new()];
     }
-    let mut search_paths = Vec::new();
+    let mut search_dirs = Vec::new();
     for dir_name in ["avatars", "emojis", "icons"] {
-        let path = media_root.join(dir_name);
-        if path.is_dir() {
-            search_paths.push(path);
+        if is_dir(dir_name) {
+            search_dirs.push(dir_name.to_string());
         }
     }
-    let channels_root = media_root.join("channels");
-    if channels_root.is_dir() {
-        let channel_specific_path = channels_root.join(export_name);
-        search_paths.push(if channel_specific_path.is_dir() {
-            channel_specific_path
+    if is_dir("channels") {
+        let channel_specific_dir = format!("channels/{export_name}");
+        search_dirs.push(if is_dir(&channel_specific_dir) {
+            channel_specific_dir
         } else {
-            channels_root
+            "channels".to_string()
         });
     }
-    if search_paths.is_empty() {
-        search_paths.push(media_root.to_path_buf());
+    if search_dirs.is_empty() {
+        search_dirs.push(String::new());
     }
-    search_paths
+    search_dirs
 }
-pub fn is_zip_file(filename: &str) -> bool {
-    filename.to_ascii_lowercase().ends_with("zip")
+pub fn locate_media_dirs(media_root: &Path, export_name: &str) -> Vec<PathBuf> {
+    let has_subdirs = fs::read_dir(media_root).ok().is_some_and(|mut dir| {
+        dir.any(|e| {
+            e.ok()
+                .is_some_and(|de| de.file_type().is_ok_and(|ft| ft.is_dir()))
+        })
+    });
+    media_dirs(
+        has_subdirs,
+        |dir| media_root.join(dir).is_dir(),
+        export_name,
+    )
+    .into_iter()
+    .map(|dir| media_root.join(dir))
+    .collect()
 }
-fn extract_zip_to_temp(zip_path: &Path) -> Result<tempfile::TempDir, String> {
+pub fn index_zip(zip_path: &Path, export_name: &str) -> Result<FileIndex, String> {
     let file = fs::File::open(zip_path).map_err(|e| format!("Error opening zip: {e}"))?;
-    let mut archive = zip::ZipArchive::new(file).map_err(|e| format!("Error reading zip: {e}"))?;
-    let tempdir = tempfile::tempdir().map_err(|e| format!("Error creating temp dir: {e}"))?;
-    let base_dir = tempdir.path();
-    let create_dir =
-        |path: &Path| fs::create_dir_all(path).map_err(|e| format!("Create dir failed: {e}"));
-    for i in 0..archive.len() {
-        let mut entry = archive
-            .by_index(i)
-            .map_err(|e| format!("Zip entry error: {e}"))?;
-        let output_path = match entry.enclosed_name() {
-            Some(path) => base_dir.join(path),
-            None => base_dir.join(entry.mangled_name()),
-        };
-        if entry.is_dir() || entry.name().ends_with('/') {
-            create_dir(&output_path)?;
+    let archive = zip::ZipArchive::new(file).map_err(|e| format!("Error reading zip: {e}"))?;
+    let entries: Vec<String> = archive
+        .file_names()
+        .filter(|name| !name.ends_with('/'))
+        .map(str::to_string)
+        .collect();
+    let has_subdirs = entries.iter().any(|name| name.contains('/'));
+    let is_dir = |dir: &str| {
+        let prefix = format!("{dir}/");
+        entries.iter().any(|name| name.starts_with(&prefix))
+    };
+    let search_dirs = media_dirs(has_subdirs, is_dir, export_name);
+    let shared: SharedZip = Arc::new(Mutex::new(archive));
+    let mut index = FileIndex::default();
+    for name in entries {
+        let in_search_dir = search_dirs
+            .iter()
+            .any(|dir| dir.is_empty() || name.starts_with(&format!("{dir}/")));
+        if !in_search_dir {
             continue;
         }
-        if let Some(parent) = output_path.parent() {
-            create_dir(parent)?;
-        }
-        let mut output_file =
-            fs::File::create(&output_path).map_err(|e| format!("Create file failed: {e}"))?;
-        io::copy(&mut entry, &mut output_file).map_err(|e| format!("Write file failed: {e}"))?;
-    }
-    Ok(tempdir)
-}
-async fn fetch_zip_to_tempfile(url: &str) -> Result<tempfile::NamedTempFile, String> {
-    let resp = reqwest::get(url)
-        .await
-        .map_err(|e| format!("Error fetching ZIP: {e}"))?;
-    if !resp.status().is_success() {
-        return Err(format!("HTTP error: {}", resp.status()));
-    }
-    let bytes = resp
-        .bytes()
-        .await
-        .map_err(|e| format!("Error reading ZIP body: {e}"))?;
-    let mut tmp =
-        tempfile::NamedTempFile::new().map_err(|e| format!("Error creating temp file: {e}"))?;
-    tmp.write_all(bytes.as_ref())
-        .map_err(|e| format!("Error writing temp ZIP: {e}"))?;
-    tmp.flush()
-        .map_err(|e| format!("Error flushing temp ZIP: {e}"))?;
-    Ok(tmp)
+        add_media_file(
+            &mut index,
+            &name,
+            MediaFile::Zip(shared.clone(), name.clone()),
+        );
+    }
+    Ok(index)
+}
+pub fn media_key(file: &MediaFile) -> PathBuf {
+    match file {
+        MediaFile::Disk(path) => path.clone(),
+        MediaFile::Zip(_, name) => PathBuf::from(name),
+    }
+}
+pub async fn media_attachment(
+    file: &MediaFile,
+    filename: &str,
+) -> Option<serenity::CreateAttachment> {
+    match file {
+        MediaFile::Disk(path) => {
+            if fs::metadata(path).ok()?.len() > MAX_UPLOAD_SIZE {
+                return None;
+            }
+            serenity::CreateAttachment::path(path)
+                .await
+                .ok()
+                .map(|mut attachment| {
+                    attachment.filename = filename.to_string();
+                    attachment
+                })
+        }
+        MediaFile::Zip(archive, name) => {
+            let (archive, name) = (archive.clone(), name.clone());
+            let data = tokio::task::spawn_blocking(move || {
+                let mut archive = archive.lock().unwrap();
+                let entry = archive.by_name(&name).ok()?;
+                if entry.size() > MAX_UPLOAD_SIZE {
+                    return None;
+                }
+                let mut data = Vec::new();
+                entry
+                    .take(MAX_UPLOAD_SIZE + 1)
+                    .read_to_end(&mut data)
+                    .ok()?;
+                (data.len() as u64 <= MAX_UPLOAD_SIZE).then_some(data)
+            })
+            .await
+            .ok()??;
+            Some(serenity::CreateAttachment::bytes(data, filename))
+        }
+    }
+}
+pub async fn source_attachment(source: &MediaSource) -> Option<serenity::CreateAttachment> {
+    match source {
+        MediaSource::Local(file, filename) => media_attachment(file, filename).await,
+        MediaSource::Fetched(data, filename) => Some(serenity::CreateAttachment::bytes(
+            data.clone(),
+            filename.as_str(),
+        )),
+        MediaSource::Remote(_) | MediaSource::Unavailable(_) => None,
+    }
+}
+pub fn media_unavailable(filename: &str) -> String {
+    format!("*Media unavailable: {filename}*")
+}
+pub fn media_size(file: &MediaFile) -> Option<u64> {
+    match file {
+        MediaFile::Disk(path) => fs::metadata(path).ok().map(|metadata| metadata.len()),
+        MediaFile::Zip(archive, name) => archive
+            .lock()
+            .unwrap()
+            .by_name(name)
+            .ok()
+            .map(|entry| entry.size()),
+    }
+}
+pub fn format_duration(duration: Duration) -> String {
+    let seconds = duration.as_secs();
+    match (seconds / 3600, seconds / 60 % 60, seconds % 60) {
+        (0, 0, s) => format!("{s}s"),
+        (0, m, s) => format!("{m}m {s}s"),
+        (h, m, _) => format!("{h}h {m}m"),
+    }
+}
+pub fn format_size(bytes: u64) -> String {
+    let megabytes = bytes as f64 / (1024.0 * 1024.0);
+    if megabytes >= 1024.0 {
+        format!("{:.1} GB", megabytes / 1024.0)
+    } else {
+        format!("{megabytes:.1} MB")
+    }
 }
 pub async fn create_file_index(
+    client: &reqwest::Client,
     media_path: &Option<String>,
-    json_path: &str,
+    export_name: &str,
+    progress: Option<&ProgressSender>,
 ) -> (Option<FileIndex>, Option<tempfile::TempDir>) {
     let path_str = match media_path {
         Some(s) => s,
         None => return (None, None),
     };
-    let export_name = extract_export_name(json_path);
-    if is_url(path_str) {
-        let tmp = match fetch_zip_to_tempfile(path_str).await {
-            Ok(t) => t,
-            Err(_) => return (None, None),
-        };
-        let tempdir = match extract_zip_to_temp(tmp.path()) {
-            Ok(t) => t,
+    let download = if is_url(path_str) {
+        match download_to_tempfile(client, path_str, "media archive", progress).await {
+            Ok(download) => Some(download),
             Err(_) => return (None, None),
-        };
-        let search_paths = locate_media_dirs(tempdir.path(), &export_name);
-        let index = scan_files(&search_paths);
-        return (Some(index), Some(tempdir));
-    }
-    let path = Path::new(path_str);
-    if is_zip_file(path_str) {
-        if !path.exists() {
+        }
+    } else {
+        None
+    };
+    let path = download
+        .as_ref()
+        .map_or(Path::new(path_str), |download| download.file.path());
+    if path.is_file() {
+        let Some(kind) = detect_archive(path) else {
             return (None, None);
+        };
+        if kind == ArchiveKind::Zip {
+            return match index_zip(path, export_name) {
+                Ok(index) => (
+                    Some(FileIndex {
+                        _download: download.map(|download| download.file),
+                        ..index
+                    }),
+                    None,
+                ),
+                Err(_) => (None, None),
+            };
         }
-        let tempdir = match extract_zip_to_temp(path) {
+        let tempdir = match extract_archive_to_temp(path, kind) {
             Ok(t) => t,
             Err(_) => return (None, None),
         };
-        let search_paths = locate_media_dirs(tempdir.path(), &export_name);
+        let search_paths = locate_media_dirs(tempdir.path(), export_name);
         let index = scan_files(&search_paths);
         return (Some(index), Some(tempdir));
     }
-    let search_paths = locate_media_dirs(path, &export_name);
+    let search_paths = locate_media_dirs(path, export_name);
     let index = scan_files(&search_paths);
     (Some(index), None)
 }
@@ -174,7 +536,7 @@ pub fn generate_footer(
     no_channel: bool,
 ) -> String {
     let mut parts = Vec::new();
-    if !no_guild {
+    if !no_guild && !export.guild.name.is_empty() {
         parts.push(export.guild.name.as_str());
     }
     if !no_category {
@@ -182,7 +544,7 @@ pub fn generate_footer(
             parts.push(category);
         }
     }
-    if !no_channel {
+    if !no_channel && !export.channel.name.is_empty() {
         parts.push(export.channel.name.as_str());
     }
     parts.join(" | ")
@@ -190,32 +552,41 @@ pub fn generate_footer(
 pub fn user_profile_url(user_id: serenity::UserId) -> String {
     format!("https://discord.com/users/{}", user_id)
 }
+pub fn author_url(author: &Author) -> String {
+    author
+        .id
+        .map_or_else(|| "https://discord.com".to_string(), user_profile_url)
+}
 pub fn create_embed_base(
     message: &MessageInfo,
     export: &Export,
     avatar_filename: Option<&String>,
     current_avatar_url: Option<&str>,
-    no_guild: bool,
-    no_category: bool,
-    no_channel: bool,
-    no_timestamp: bool,
+    options: &ImportOptions,
     accent_color_value: Option<u32>,
 ) -> serenity::CreateEmbed {
-    let mut author_builder = serenity::CreateEmbedAuthor::new(&message.author.name)
-        .url(user_profile_url(message.author.id));
+    let mut author_builder = serenity::CreateEmbedAuthor::new(&message.author.name);
+    if let Some(author_id) = message.author.id {
+        author_builder = author_builder.url(user_profile_url(author_id));
+    }
     if let Some(icon_url) = current_avatar_url {
         author_builder = author_builder.icon_url(icon_url.to_string());
     } else if let Some(filename) = avatar_filename {
         author_builder = author_builder.icon_url(format!("attachment://{filename}"));
-    } else {
+    } else if is_url(&message.author.avatar_url) {
         author_builder = author_builder.icon_url(&message.author.avatar_url);
     }
-    let footer_text = generate_footer(export, no_guild, no_category, no_channel);
+    let footer_text = generate_footer(
+        export,
+        options.no_guild,
+        options.no_category,
+        options.no_channel,
+    );
     let timestamp_str = message
         .timestamp_edited
         .as_deref()
         .unwrap_or(&message.timestamp);
-    let timestamp = if no_timestamp {
+    let timestamp = if options.no_timestamp {
         None
     } else {
         serenity::Timestamp::parse(timestamp_str).ok()
@@ -234,58 +605,161 @@ pub fn create_embed_base(
     }
     embed
 }
-pub fn find_avatar(
-    author_id: &serenity::UserId,
-    file_index: &FileIndex,
-) -> Option<(PathBuf, String)> {
-    IMAGE_EXTENSIONS.iter().find_map(|ext| {
-        let filename = format!("{author_id}.{ext}");
-        file_index
-            .get(&filename)
-            .and_then(|paths| paths.first())
-            .map(|path| (path.clone(), filename))
-    })
-}
-pub fn find_local_files(
-    filename: &str,
-    file_index: &FileIndex,
-    seen_paths: &mut HashSet<PathBuf>,
-) -> Vec<(PathBuf, String)> {
-    let mut found_files = Vec::new();
-    if let Some(path) = file_index
-        .get(&filename.to_ascii_lowercase())
-        .and_then(|paths| paths.iter().find(|path| seen_paths.insert((*path).clone())))
+fn discord_attachment_id(url: &str) -> Option<&str> {
+    let mut segments = url.split(['?', '#']).next()?.split('/');
+    segments.find(|segment| *segment == "attachments")?;
+    let _channel_id = segments.next()?;
+    segments
+        .next()
+        .filter(|id| snowflakes(id).next() == Some(*id))
+}
+fn normalize_cdn_url(url: &str) -> String {
+    let Ok(parsed) = reqwest::Url::parse(url) else {
+        return url.to_string();
+    };
+    if !parsed
+        .host_str()
+        .is_some_and(|host| host.eq_ignore_ascii_case("cdn.discordapp.com"))
     {
-        found_files.push((path.clone(), filename.to_string()));
-        if let (Some(dir), Some(stem), Some(ext)) = (
-            path.parent(),
-            path.file_stem().and_then(|s| s.to_str()),
-            path.extension().and_then(|s| s.to_str()),
-        ) {
-            for i in 1.. {
-                let variant_path = dir.join(format!("{stem}_{i:03}.{ext}"));
-                if !variant_path.exists() {
-                    break;
-                }
-                if seen_paths.insert(variant_path.clone()) {
-                    found_files.push((
-                        variant_path.clone(),
-                        variant_path
-                            .file_name()
-                            .unwrap()
-                            .to_string_lossy()
-                            .into_owned(),
-                    ));
-                }
+        return url.to_string();
+    }
+    let query: Vec<String> = parsed
+        .query_pairs()
+        .filter(|(key, _)| !["ex", "is", "hm"].contains(&key.as_ref()))
+        .map(|(key, value)| format!("{key}={value}"))
+        .collect();
+    let path = url.split(['?', '#']).next().unwrap_or(url);
+    format!("{path}{}", query.join("&"))
+}
+fn url_hashes(url: &str) -> Vec<String> {
+    let sha256 = |text: &str| format!("{:x}", Sha256::digest(text.as_bytes()));
+    let mut hashes = vec![sha256(url)];
+    for variant in [normalize_cdn_url(url), url.to_string()] {
+        hashes.push(sha256(&variant)[..5].to_string());
+    }
+    hashes.dedup();
+    hashes
+}
+fn has_extension(file: &MediaFile, file_name: &str) -> bool {
+    let extension = |path: &Path| {
+        path.extension()
+            .map(|ext| ext.to_string_lossy().to_ascii_lowercase())
+    };
+    extension(&media_key(file)) == extension(Path::new(file_name))
+}
+fn pick_match(candidates: Vec<&MediaFile>) -> Option<MediaMatch> {
+    match candidates.as_slice() {
+        [] => None,
+        [file] => Some(MediaMatch::Found((*file).clone())),
+        _ => Some(MediaMatch::Ambiguous(candidates.len())),
+    }
+}
+fn resolve_exact(
+    url: &str,
+    id: Option<&str>,
+    file_name: &str,
+    index: &FileIndex,
+) -> Option<MediaMatch> {
+    let lowercase_name = file_name.to_ascii_lowercase();
+    if !url.is_empty() && !is_url(url) {
+        let relative = url.replace('\\', "/").to_ascii_lowercase();
+        let candidates: Vec<&MediaFile> = index
+            .files
+            .get(&file_name_from_url(url).to_ascii_lowercase())
+            .into_iter()
+            .flatten()
+            .filter(|file| {
+                let key = media_key(file).to_string_lossy().replace('\\', "/");
+                key.to_ascii_lowercase().ends_with(&relative)
+            })
+            .collect();
+        if let Some(found) = pick_match(candidates) {
+            return Some(found);
+        }
+    }
+    if let Some(files) = id
+        .or_else(|| discord_attachment_id(url))
+        .and_then(|id| index.ids.get(id))
+    {
+        let mut candidates: Vec<&MediaFile> = files.iter().collect();
+        if candidates.len() > 1 {
+            candidates.retain(|file| {
+                let key = media_key(file).to_string_lossy().to_ascii_lowercase();
+                key.contains(&lowercase_name)
+            });
+        }
+        if let Some(found) = pick_match(candidates) {
+            return Some(found);
+        }
+    }
+    if is_url(url) {
+        for hash in url_hashes(url) {
+            let candidates: Vec<&MediaFile> = index
+                .hashes
+                .get(&hash)
+                .into_iter()
+                .flatten()
+                .filter(|file| has_extension(file, file_name))
+                .collect();
+            if let Some(found) = pick_match(candidates) {
+                return Some(found);
             }
         }
     }
-    found_files
+    None
+}
+pub fn resolve_media(
+    url: &str,
+    id: Option<&str>,
+    file_name: &str,
+    index: &FileIndex,
+) -> MediaMatch {
+    if let Some(found) = resolve_exact(url, id, file_name, index) {
+        return found;
+    }
+    let lowercase_name = file_name.to_ascii_lowercase();
+    let mut candidates: Vec<&MediaFile> = index
+        .files
+        .get(&lowercase_name)
+        .into_iter()
+        .flatten()
+        .collect();
+    if let Some((stem, ext)) = lowercase_name.rsplit_once('.') {
+        for i in 1.. {
+            let Some(files) = index.files.get(&format!("{stem}_{i:03}.{ext}")) else {
+                break;
+            };
+            candidates.extend(files);
+        }
+    }
+    pick_match(candidates).unwrap_or(MediaMatch::Missing)
+}
+pub fn find_avatar(author: &Author, file_index: &FileIndex) -> Option<(MediaFile, String)> {
+    if let Some(avatar) = author.id.and_then(|author_id| {
+        IMAGE_EXTENSIONS.iter().find_map(|ext| {
+            let filename = format!("{author_id}.{ext}");
+            file_index
+                .files
+                .get(&filename)
+                .and_then(|files| files.first())
+                .map(|file| (file.clone(), filename))
+        })
+    }) {
+        return Some(avatar);
+    }
+    let filename = file_name_from_url(&author.avatar_url);
+    if !is_image_file(&filename) {
+        return None;
+    }
+    match resolve_exact(&author.avatar_url, None, &filename, file_index)? {
+        MediaMatch::Found(file) => Some((file, filename)),
+        _ => None,
+    }
 }
 pub fn collect_sources(
     message: &MessageInfo,
     file_index: &Option<FileIndex>,
-    seen_paths: &mut HashSet<PathBuf>,
+    ambiguous: &mut Vec<String>,
     filter: impl Fn(&AttachmentInfo) -> bool,
 ) -> Vec<MediaSource> {
     let mut sources = Vec::new();
@@ -293,20 +767,60 @@ pub fn collect_sources(
         if !filter(attachment_info) {
             continue;
         }
-        let mut found_local = false;
-        if let Some(index) = file_index {
-            for (path, filename) in find_local_files(&attachment_info.file_name, index, seen_paths)
-            {
-                sources.push(MediaSource::Local(path, filename));
-                found_local = true;
+        let id = Some(attachment_info.id.as_str()).filter(|id| !id.is_empty());
+        let found = file_index.as_ref().map_or(MediaMatch::Missing, |index| {
+            resolve_media(&attachment_info.url, id, &attachment_info.file_name, index)
+        });
+        match found {
+            MediaMatch::Found(file) => {
+                sources.push(MediaSource::Local(file, attachment_info.file_name.clone()));
+                continue;
             }
+            MediaMatch::Ambiguous(count) => ambiguous.push(format!(
+                "`{}` in message {} ({count} files)",
+                attachment_info.file_name, message.id
+            )),
+            MediaMatch::Missing => {}
         }
-        if !found_local {
-            sources.push(MediaSource::Remote(attachment_info.url.clone()));
-        }
+        sources.push(if is_url(&attachment_info.url) {
+            MediaSource::Remote(attachment_info.url.clone())
+        } else {
+            MediaSource::Unavailable(attachment_info.file_name.clone())
+        });
     }
     sources
 }
+pub fn format_ambiguous_media(ambiguous: &[String]) -> String {
+    let mut report = format!(
+        "{} attachment(s) matched several media files and were linked instead:",
+        ambiguous.len()
+    );
+    for (i, entry) in ambiguous.iter().enumerate() {
+        let more = format!("\n...and {} more", ambiguous.len() - i);
+        if report.len() + entry.len() + 3 + more.len() > MAX_MESSAGE_LENGTH {
+            report.push_str(&more);
+            break;
+        }
+        report.push_str("\n- ");
+        report.push_str(entry);
+    }
+    report
+}
+pub fn split_message(text: &str) -> Vec<String> {
+    let mut chunks = Vec::new();
+    let mut chunk = String::new();
+    for line in text.lines() {
+        if !chunk.is_empty() && chunk.chars().count() + line.chars().count() >= MAX_MESSAGE_LENGTH {
+            chunks.push(std::mem::take(&mut chunk));
+        }
+        chunk.push_str(line);
+        chunk.push('\n');
+    }
+    if !chunk.trim().is_empty() {
+        chunks.push(chunk);
+    }
+    chunks
+}
 pub fn replace_mentions(content: &str, mentions: &[Mention], no_mentions: bool) -> String {
     if no_mentions {
         return content.to_string();
@@ -355,6 +869,16 @@ pub fn format_emoji(emoji: &EmojiInfo) -> String {
     }
     emoji.name.clone()
 }
+pub fn format_reaction_user(user_value: &serde_json::Value) -> Option<String> {
+    let user = user_value.as_object()?;
+    if let Some(id_str) = user.get("id").and_then(|id_val| id_val.as_str()) {
+        return Some(format!("<@{}>", id_str));
+    }
+    user.get("nickname")
+        .or_else(|| user.get("name"))
+        .and_then(|name_val| name_val.as_str())
+        .map(|name| name.to_string())
+}
 pub fn format_reaction_users(reactions: &[ReactionInfo]) -> String {
     reactions
         .iter()
@@ -380,38 +904,83 @@ pub fn format_reaction_users(reactions: &[ReactionInfo]) -> String {
         .collect::<Vec<String>>()
         .join("\n")
 }
+pub fn format_reaction_summary(reactions: &[ReactionInfo]) -> String {
+    let mut summary = String::new();
+    for reaction in reactions {
+        let item = format!(
+            "{} {}",
+            format_emoji(&reaction.emoji),
+            get_reaction_count(reaction)
+        );
+        let separator = if summary.is_empty() { "" } else { " · " };
+        if summary.len() + separator.len() + item.len() > MAX_FIELD_LENGTH {
+            break;
+        }
+        summary.push_str(separator);
+        summary.push_str(&item);
+    }
+    summary
+}
+pub fn with_reaction_summary(
+    embed: serenity::CreateEmbed,
+    reaction_summary: Option<&str>,
+) -> serenity::CreateEmbed {
+    match reaction_summary {
+        Some(summary) => embed.field("Reactions", summary, false),
+        None => embed,
+    }
+}
 pub fn emoji_to_reaction_type(emoji: &EmojiInfo) -> serenity::ReactionType {
     if let Some(id_str) = &emoji.id {
         if let Ok(id) = id_str.parse::<u64>() {
             return serenity::ReactionType::Custom {
                 animated: emoji.is_animated,
                 id: serenity::EmojiId::new(id),
-                name: Some(format_emoji(&emoji)),
+                name: Some(format_emoji(emoji)),
             };
         }
     }
-    serenity::ReactionType::Unicode(format_emoji(&emoji))
+    serenity::ReactionType::Unicode(format_emoji(emoji))
+}
+pub fn visible_button_count(reaction_count: usize) -> usize {
+    let max_buttons = MAX_BUTTONS_PER_ROW * MAX_ACTION_ROWS;
+    if reaction_count > max_buttons {
+        max_buttons - 1
+    } else {
+        reaction_count
+    }
 }
 pub fn create_buttons(
     reactions: &[ReactionInfo],
     disable_button: bool,
 ) -> Vec<serenity::CreateButton> {
-    reactions
+    let visible = visible_button_count(reactions.len());
+    let mut buttons: Vec<serenity::CreateButton> = reactions[..visible]
         .iter()
-        .map(|reaction| {
+        .enumerate()
+        .map(|(index, reaction)| {
             let count = get_reaction_count(reaction);
             let label = format!("{PADDING}{count}");
-            let mut button =
-                serenity::CreateButton::new(format!("dummy_reaction_{}", reaction.emoji.code))
-                    .emoji(emoji_to_reaction_type(&reaction.emoji))
-                    .label(label)
-                    .style(serenity::ButtonStyle::Secondary);
-            if disable_button {
-                button = button.disabled(true);
-            }
-            button
+            serenity::CreateButton::new(format!("{REACTION_BUTTON_PREFIX}{index}"))
+                .emoji(emoji_to_reaction_type(&reaction.emoji))
+                .label(label)
+                .style(serenity::ButtonStyle::Secondary)
         })
-        .collect()
+        .collect();
+    if visible < reactions.len() {
+        buttons.push(
+            serenity::CreateButton::new(format!("{REACTION_BUTTON_PREFIX}{MORE_REACTIONS_KEY}"))
+                .label(format!("+{} more", reactions.len() - visible))
+                .style(serenity::ButtonStyle::Secondary),
+        );
+    }
+    if disable_button {
+        buttons = buttons
+            .into_iter()
+            .map(|button| button.disabled(true))
+            .collect();
+    }
+    buttons
 }
 pub fn create_reactions(reactions: &[ReactionInfo]) -> Vec<serenity::ReactionType> {
     reactions
@@ -428,26 +997,85 @@ pub fn with_reaction_buttons(
     if button && !reactions.is_empty() {
         let buttons = create_buttons(reactions, disable_button);
         if !buttons.is_empty() {
-            reply = reply.components(vec![serenity::CreateActionRow::Buttons(buttons)]);
+            let rows = buttons
+                .chunks(MAX_BUTTONS_PER_ROW)
+                .map(|row| serenity::CreateActionRow::Buttons(row.to_vec()))
+                .collect();
+            reply = reply.components(rows);
         }
     }
     reply
 }
-pub async fn load_export(json_path: &str) -> Result<Export, String> {
-    let content = if is_url(json_path) {
-        let resp = reqwest::get(json_path)
-            .await
-            .map_err(|e| format!("Error fetching JSON: {e}"))?;
-        if !resp.status().is_success() {
-            return Err(format!("HTTP error: {}", resp.status()));
+pub fn store_reactions(reactions: &[ReactionInfo]) -> Vec<StoredReaction> {
+    reactions
+        .iter()
+        .map(|reaction| StoredReaction {
+            emoji: format_emoji(&reaction.emoji),
+            count: get_reaction_count(reaction),
+            users: reaction
+                .users
+                .iter()
+                .filter_map(format_reaction_user)
+                .collect(),
+        })
+        .collect()
+}
+pub fn format_stored_reaction(reaction: &StoredReaction) -> String {
+    let header = format!("{} {}", reaction.emoji, reaction.count);
+    if reaction.users.is_empty() {
+        return format!("{header}\nNo users were exported for this reaction.");
+    }
+    let mut users = String::new();
+    let mut listed = 0;
+    for user in &reaction.users {
+        let separator = if listed == 0 { "" } else { ", " };
+        if header.len() + users.len() + separator.len() + user.len() + 32 > MAX_MESSAGE_LENGTH {
+            break;
         }
-        resp.text()
-            .await
-            .map_err(|e| format!("Error reading response body: {e}"))?
-    } else {
-        fs::read_to_string(json_path).map_err(|e| format!("Error reading JSON file: {e}"))?
+        users.push_str(separator);
+        users.push_str(user);
+        listed += 1;
+    }
+    let hidden = (reaction.count as usize).max(reaction.users.len()) - listed;
+    if hidden > 0 {
+        users.push_str(&format!(" and {hidden} more"));
+    }
+    format!("{header}\n{users}")
+}
+pub fn format_more_reactions(reactions: &[StoredReaction]) -> String {
+    let mut text = String::new();
+    for (listed, reaction) in reactions.iter().enumerate() {
+        let line = format!("{} {}", reaction.emoji, reaction.count);
+        if text.len() + line.len() + 32 > MAX_MESSAGE_LENGTH {
+            text.push_str(&format!("\nand {} more", reactions.len() - listed));
+            break;
+        }
+        if !text.is_empty() {
+            text.push('\n');
+        }
+        text.push_str(&line);
+    }
+    text
+}
+pub fn load_reaction_store(path: &str) -> HashMap<serenity::MessageId, Vec<StoredReaction>> {
+    let mut store = HashMap::new();
+    let Ok(content) = fs::read_to_string(path) else {
+        return store;
     };
-    serde_json::from_str(&content).map_err(|e| format!("Error parsing JSON: {e}"))
+    for line in content.lines() {
+        if let Ok(entry) = serde_json::from_str::<StoredReactions>(line) {
+            store.insert(entry.message_id, entry.reactions);
+        }
+    }
+    store
+}
+pub fn append_reaction_store(path: &str, entry: &StoredReactions) -> io::Result<()> {
+    let line = serde_json::to_string(entry)?;
+    let mut file = fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)?;
+    writeln!(file, "{line}")
 }
 pub fn ask_token() -> String {
     print!("Enter DISCORD_TOKEN: ");